@@ -0,0 +1,68 @@
+use crate::ray::Ray;
+use crate::vector::Point3;
+
+
+/// Axis-aligned bounding box.
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+    pub minimum: Point3,
+    pub maximum: Point3,
+}
+
+impl Aabb {
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Aabb {
+            minimum: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            maximum: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    /// The smallest box containing both `a` and `b`.
+    pub fn surrounding_box(a: &Aabb, b: &Aabb) -> Self {
+        Aabb {
+            minimum: Point3::new(a.minimum.x.min(b.minimum.x), a.minimum.y.min(b.minimum.y), a.minimum.z.min(b.minimum.z)),
+            maximum: Point3::new(a.maximum.x.max(b.maximum.x), a.maximum.y.max(b.maximum.y), a.maximum.z.max(b.maximum.z)),
+        }
+    }
+
+    /// Grow any side thinner than `delta`, so that flat objects still get a
+    /// box rays can hit.
+    pub fn pad(self: &Self, delta: f64) -> Self {
+        let mut padded = *self;
+        for axis in 0..3 {
+            if self.maximum[axis] - self.minimum[axis] < delta {
+                padded.minimum[axis] = self.minimum[axis] - delta / 2.0;
+                padded.maximum[axis] = self.maximum[axis] + delta / 2.0;
+            }
+        }
+        padded
+    }
+
+    /// Slab test.
+    pub fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit_interval(ray, t_min, t_max).is_some()
+    }
+
+    /// The part of `t_min..t_max` the ray spends inside the box, if any.
+    pub fn hit_interval(self: &Self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
+        for axis in 0..3 {
+            let inverse_direction = 1.0 / ray.direction[axis];
+            let mut t0 = (self.minimum[axis] - ray.origin[axis]) * inverse_direction;
+            let mut t1 = (self.maximum[axis] - ray.origin[axis]) * inverse_direction;
+            if inverse_direction < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some((t_min, t_max))
+    }
+}
@@ -0,0 +1,150 @@
+// Normal and bump mapping: detail too fine to model, painted on by tilting
+// the shading normal rather than moving the surface. Both work in the frame
+// of the surface's texture co-ordinates, from the tangents `dpdu` and `dpdv`
+// that shapes put in their hit records, and tilt the normal that materials
+// scatter about without touching the geometry itself.
+
+use crate::hittable::HitRecord;
+use crate::onb::Onb;
+use crate::texture::Texture;
+use crate::vector::Vec3;
+
+
+/// Step in `(u, v)` for the finite differences of height maps.
+const DELTA: f64 = 1.0 / 2048.0;
+
+/// The smallest cosine allowed between a tilted normal and either the
+/// geometric normal or the direction back along the ray.
+const MIN_COSINE: f64 = 0.01;
+
+
+#[derive(Clone, PartialEq)]
+pub enum Bump {
+    /// NormalMap(texture). Tangent-space normals, encoded as is usual with
+    /// each component mapped from `[-1, 1]` to `[0, 1]`, so flat is
+    /// `(0.5, 0.5, 1)`.
+    NormalMap(Texture),
+
+    /// Height(texture, scale). Heights from the texture's luminance, times
+    /// `scale` in world units.
+    Height(Texture, f64),
+}
+
+impl Bump {
+    /// Tilt `rec.normal` for a ray coming in along `direction`.
+    pub fn apply(self: &Self, rec: &mut HitRecord, direction: &Vec3) {
+        // Work with the outward normal, as the maps are painted on the
+        // outside of surfaces.
+        let outward = if rec.front_face { rec.normal } else { -rec.normal };
+
+        // Shapes that don't know their tangents get an arbitrary frame.
+        let (dpdu, dpdv) = match rec.dpdu.near_zero() || rec.dpdv.near_zero() {
+            true => {
+                let frame = Onb::build_from_w(&outward);
+                (frame.u, frame.v)
+            }
+            false => (rec.dpdu, rec.dpdv),
+        };
+
+        let tilted = match self {
+            Bump::NormalMap(texture) => {
+                let c = texture.value(rec.u, rec.v, &rec.p);
+
+                let tangent = (dpdu - outward.dot(&dpdu) * outward).unit_vector();
+                let mut bitangent = outward.cross(tangent);
+                if bitangent.dot(&dpdv) < 0.0 {
+                    bitangent = -bitangent;
+                }
+
+                (2.0 * c.x - 1.0) * tangent + (2.0 * c.y - 1.0) * bitangent + (2.0 * c.z - 1.0) * outward
+            }
+            Bump::Height(texture, scale) => {
+                let height = |du: f64, dv: f64| {
+                    let p = rec.p + du * dpdu + dv * dpdv;
+                    texture.value(rec.u + du, rec.v + dv, &p).luminance() * scale
+                };
+                let h = height(0.0, 0.0);
+                let dhdu = (height(DELTA, 0.0) - h) / DELTA;
+                let dhdv = (height(0.0, DELTA) - h) / DELTA;
+
+                // The displaced surface's tangents, crossed.
+                let normal = (dpdu + dhdu * outward).cross(dpdv + dhdv * outward);
+                match normal.dot(&outward) < 0.0 {
+                    true => -normal,
+                    false => normal,
+                }
+            }
+        };
+
+        if tilted.near_zero() {
+            return;
+        }
+
+        let mut normal = if rec.front_face { tilted.unit_vector() } else { -tilted.unit_vector() };
+
+        // A normal tilted under the geometric surface, or away from the
+        // viewer, would have the material scatter light from below it, so
+        // tip it back until it's just above both.
+        let geometric_normal = rec.normal;
+        let wo = -direction.unit_vector();
+        for towards in [geometric_normal, wo] {
+            let cos = normal.dot(&towards);
+            if cos < MIN_COSINE {
+                normal = (normal + (MIN_COSINE - cos) * towards).unit_vector();
+            }
+        }
+
+        rec.geometric_normal = Some(geometric_normal);
+        rec.normal = normal;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{Color, Point3};
+
+    fn flat_hit() -> HitRecord {
+        let mut rec = HitRecord::default();
+        rec.p = Point3::new(0.2, 0.7, 0.0);
+        rec.normal = Vec3::new(0.0, 0.0, 1.0);
+        rec.front_face = true;
+        rec.u = 0.2;
+        rec.v = 0.7;
+        rec.dpdu = Vec3::new(1.0, 0.0, 0.0);
+        rec.dpdv = Vec3::new(0.0, 1.0, 0.0);
+        rec
+    }
+
+    #[test]
+    fn test_flat_maps_leave_the_normal() {
+        let direction = Vec3::new(0.3, -0.2, -1.0);
+        let flat = [
+            Bump::NormalMap(Texture::Constant(Color::new(0.5, 0.5, 1.0))),
+            Bump::Height(Texture::Constant(Color::new(0.7, 0.7, 0.7)), 2.0),
+        ];
+
+        for bump in flat {
+            let mut rec = flat_hit();
+            bump.apply(&mut rec, &direction);
+            assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9, "{}", rec.normal);
+        }
+    }
+
+    #[test]
+    fn test_tilted_normal_stays_above_the_surface() {
+        // Tipped almost flat along x, seen from low down on the other side.
+        let bump = Bump::NormalMap(Texture::Constant(Color::new(1.0, 0.5, 0.51)));
+        let wo = Vec3::new(-1.0, 0.0, 0.05).unit_vector();
+
+        let mut rec = flat_hit();
+        bump.apply(&mut rec, &-wo);
+        assert_eq!(rec.geometric_normal, Some(Vec3::new(0.0, 0.0, 1.0)));
+        assert!(rec.normal.z >= 0.5 * MIN_COSINE && rec.normal.dot(&wo) >= 0.5 * MIN_COSINE, "{}", rec.normal);
+
+        // Light only leaves on the side the geometry says.
+        assert!(!rec.sides_agree(&Vec3::new(-1.0, 0.0, -0.01)));
+        assert!(rec.sides_agree(&Vec3::new(0.0, 0.0, 1.0)));
+    }
+}
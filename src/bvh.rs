@@ -0,0 +1,102 @@
+use std::cmp::Ordering;
+
+use crate::aabb::Aabb;
+use crate::hittable::{hit_opaque, HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::vector::Point3;
+
+
+/// Bounding volume hierarchy over objects that all have bounding boxes.
+pub struct BvhNode {
+    left: Box<dyn Hittable + Send + Sync>,
+
+    /// `None` only for a node holding a single object.
+    right: Option<Box<dyn Hittable + Send + Sync>>,
+
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    /// Panics if `objects` is empty or any object is unbounded.
+    pub fn new(mut objects: Vec<Box<dyn Hittable + Send + Sync>>) -> Self {
+        assert!(!objects.is_empty(), "Can't build a BVH over nothing");
+
+        let boxes: Vec<Aabb> = objects
+            .iter()
+            .map(|o| o.bounding_box().expect("BVH objects must have a bounding box"))
+            .collect();
+        let bbox = boxes.iter().skip(1).fold(boxes[0], |a, b| Aabb::surrounding_box(&a, b));
+
+        if objects.len() == 1 {
+            return BvhNode { left: objects.pop().unwrap(), right: None, bbox };
+        }
+
+        // Split at the median along the axis the centers are spread out most on.
+        let centers: Vec<Point3> = boxes.iter().map(|b| (b.minimum + b.maximum) / 2.0).collect();
+        let spread = centers
+            .iter()
+            .skip(1)
+            .fold(Aabb::new(centers[0], centers[0]), |a, c| Aabb::surrounding_box(&a, &Aabb::new(*c, *c)));
+        let extent = spread.maximum - spread.minimum;
+        let axis = match (extent.x > extent.y, extent.x > extent.z, extent.y > extent.z) {
+            (true, true, _) => 0,
+            (false, _, true) => 1,
+            _ => 2,
+        };
+
+        let mut keyed: Vec<(f64, Box<dyn Hittable + Send + Sync>)> = centers
+            .iter()
+            .map(|c| c[axis])
+            .zip(objects)
+            .collect();
+        keyed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut objects: Vec<Box<dyn Hittable + Send + Sync>> = keyed.into_iter().map(|(_, o)| o).collect();
+        let right_objects = objects.split_off(objects.len() / 2);
+
+        BvhNode {
+            left: Self::child(objects),
+            right: Some(Self::child(right_objects)),
+            bbox,
+        }
+    }
+
+    fn child(mut objects: Vec<Box<dyn Hittable + Send + Sync>>) -> Box<dyn Hittable + Send + Sync> {
+        match objects.len() {
+            1 => objects.pop().unwrap(),
+            _ => Box::new(BvhNode::new(objects)),
+        }
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return false;
+        }
+
+        let hit_left = hit_opaque(&self.left, ray, t_min, t_max, rec);
+        let hit_right = match &self.right {
+            Some(right) => hit_opaque(right, ray, t_min, if hit_left { rec.t } else { t_max }, rec),
+            None => false,
+        };
+
+        hit_left || hit_right
+    }
+
+    fn transmittance(self: &Self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return 1.0;
+        }
+
+        let left = self.left.transmittance(ray, t_min, t_max);
+        match (&self.right, left > 0.0) {
+            (Some(right), true) => left * right.transmittance(ray, t_min, t_max),
+            _ => left,
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+}
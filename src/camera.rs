@@ -1,4 +1,5 @@
 use crate::{Vec3, Point3, Ray};
+use crate::sampler::Sampler;
 use crate::utility;
 
 pub struct Camera {
@@ -44,7 +45,12 @@ impl Camera {
         }
     }
 
-    pub fn get_ray(self: &Self, s: f64, t: f64) -> Ray {
-        Ray::new(self.origin, self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin)
+    /// The ray through `(s, t)` on the viewport, from a point on the lens
+    /// picked by `sampler`.
+    pub fn get_ray(self: &Self, s: f64, t: f64, sampler: &mut Sampler) -> Ray {
+        let rd = self.lens_radius * Vec3::sample_in_unit_disk(sampler.get_2d());
+        let offset = self.u * rd.x + self.v * rd.y;
+
+        Ray::new(self.origin + offset, self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset)
     }
 }
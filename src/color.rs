@@ -1,8 +1,15 @@
 use std::f64::INFINITY;
 
+use crate::environment::Environment;
+use crate::scene::Scene;
 use crate::hittable::{Hittable, HitRecord};
+use crate::interior::InteriorStack;
+use crate::material::ScatterContext;
+use crate::spectrum::{Channels, SampledWavelengths};
+use crate::subsurface;
 use crate::vector::Vec3;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::image_writer::Color32;
 use crate::vector::{Point3, Color};
 use crate::utility::clamp;
@@ -22,37 +29,280 @@ fn intersects_sphere(center: Point3, radius: f64, ray: &Ray) -> f64  {
 
 }
 
-pub fn make_ray_color<T>(ray: Ray, world: &T, depth: u32) -> Color 
+/// Sampler dimensions set aside for each hit along a path: enough for
+/// picking from a `Mix`, the start of a subsurface walk, scattering and
+/// sampling lights at one surface.
+const DIMENSIONS_PER_VERTEX: u32 = 16;
+
+pub fn make_ray_color<T>(ray: Ray, scene: &Scene<T>, depth: u32, sampler: &mut Sampler) -> Color 
 where T: Hittable
 {
-    let mut rec = HitRecord::default();
-    
-    if depth <= 0 {
-        return Color::new(0.0, 0.0, 0.0);
-    }
+    trace(ray, scene, depth, &mut Channels::Rgb, sampler)
+}
+
+/// As `make_ray_color`, but following the ray at a few random wavelengths
+/// rather than in RGB. Returns linear sRGB all the same.
+pub fn make_ray_color_spectral<T>(ray: Ray, scene: &Scene<T>, depth: u32, sampler: &mut Sampler) -> Color
+where T: Hittable
+{
+    let mut channels = Channels::Spectral(SampledWavelengths::sample(sampler.get_1d()));
+    let radiance = trace(ray, scene, depth, &mut channels, sampler);
+    channels.to_rgb(radiance)
+}
+
+/// Radiance along `ray` in `channels`, which the path may narrow down to
+/// a single wavelength.
+fn trace<T>(ray: Ray, scene: &Scene<T>, depth: u32, channels: &mut Channels, sampler: &mut Sampler) -> Color
+where T: Hittable
+{
+    let world = &scene.world;
+    let environment = &scene.environment;
+
+    let mut ray = ray;
+    let mut radiance = Color::new(0.0, 0.0, 0.0);
+    let mut throughput = Color::new(1.0, 1.0, 1.0);
+
+    // Solid angle PDF of the bounce that produced `ray`.
+    // `None` for camera rays and specular bounces, which light sampling can't reproduce.
+    let mut bsdf_pdf: Option<f64> = None;
+
+    // Where that bounce was. Passing through a false hit carries `ray` on
+    // from the surface it skipped, but light sampling would have started here.
+    let mut vertex = ray.origin;
+
+    // Dielectrics the path is inside.
+    let mut interior = InteriorStack::new();
+
+    let first_dimension = sampler.dimension();
+    let mut vertices = 0;
+
+    let mut bounces = 0;
+    while bounces < depth {
+        sampler.start_dimension(first_dimension + vertices * DIMENSIONS_PER_VERTEX);
+        vertices += 1;
+
+        let mut rec = HitRecord::default();
+
+        let hit = world.hit(&ray, 0.001, INFINITY, &mut rec);
+
+        // Absorption by whatever the ray travelled through to get here.
+        if let (true, Some(medium)) = (hit, interior.current()) {
+            throughput *= channels.lift(medium.transmittance(rec.t * ray.direction.length()));
+        }
+
+        if !hit {
+            // The ray escaped the world.
+            let weight = match bsdf_pdf {
+                Some(pdf) => power_heuristic(pdf, environment.pdf(&ray.direction)),
+                None => 1.0,
+            };
+            radiance += throughput * channels.lift(environment.value(&ray.direction)) * weight;
+            break;
+        }
+
+        // Settle blends first, so everything below sees a single material.
+        rec.material = rec.material.resolve(&rec, sampler);
+
+        // Surfaces hidden inside a higher priority dielectric aren't really
+        // there, but passing through them still changes what we're inside.
+        if rec.material.has_interior() && interior.is_false_hit(&rec.material) {
+            interior.cross(&rec.material, rec.front_face);
+            ray = Ray::new(rec.p, ray.direction);
+            continue;
+        }
+        bounces += 1;
+
+        // Bump maps tilt the normal that materials see, but which side of
+        // the surface light ends up on is still down to the geometry.
+        let mut geometric_normal = rec.normal;
+        if let Some(bump) = rec.material.bump.clone() {
+            bump.apply(&mut rec, &ray.direction);
+        }
+
+        let emitted = rec.material.emitted(&rec);
+        if !emitted.near_zero() {
+            let weight = match bsdf_pdf {
+                Some(pdf) => power_heuristic(pdf, area_light_pdf(scene, &vertex, &ray.direction)),
+                None => 1.0,
+            };
+            radiance += throughput * channels.lift(emitted) * weight;
+        }
+
+        // Light getting into a subsurface material wanders about inside, and
+        // carries on from wherever it comes back out.
+        if let (Some((albedo, mean_free_path, phase)), true) = (rec.material.subsurface(), rec.front_face) {
+            match subsurface::random_walk(&rec, albedo, mean_free_path, &phase, world, channels, sampler) {
+                Some((arriving, exit, weight)) => {
+                    throughput *= weight;
+                    ray = arriving;
+                    geometric_normal = exit.normal;
+                    rec = exit;
+                }
+                None => break,
+            }
+        }
 
-    if world.hit(&ray, 0.001, INFINITY, &mut rec) {
         let mut scattered: Ray = Ray::default();
         let mut attentuation: Color = Color::default();
 
-        if rec.material.scatter(&ray, &rec, &mut attentuation, &mut scattered) {
-            return attentuation * make_ray_color(scattered, world, depth-1);
+        // Only one wavelength can follow a dispersed ray.
+        if rec.material.is_dispersive() {
+            channels.terminate_secondary();
+        }
+
+        let wavelength = channels.wavelength();
+        let outside_ior = interior.outside_ior(&rec.material, rec.front_face, wavelength);
+        let mut context = ScatterContext { outside_ior, channels: &channels, sampler };
+        if !rec.material.scatter(&ray, &rec, &mut context, &mut attentuation, &mut scattered) || !rec.sides_agree(&scattered.direction) {
+            break;
+        }
+
+        // Transmitted into or out of the material.
+        if rec.material.has_interior() && scattered.direction.dot(&geometric_normal) < 0.0 {
+            interior.cross(&rec.material, rec.front_face);
+        }
+
+        if !rec.material.is_specular() {
+            radiance += throughput * sample_environment(&ray, &rec, outside_ior, world, environment, channels, sampler);
+            radiance += throughput * sample_lights(&ray, &rec, outside_ior, scene, channels);
+            radiance += throughput * sample_area_lights(&ray, &rec, outside_ior, scene, channels, sampler);
+        }
+
+        bsdf_pdf = match rec.material.is_specular() {
+            true => None,
+            false => Some(rec.material.pdf(&ray, &rec, outside_ior, &channels, &scattered.direction)),
+        };
+        throughput *= attentuation;
+        ray = scattered;
+        vertex = ray.origin;
+    }
+
+    radiance
+}
+
+/// Direct lighting from the environment at `rec`, sampled from the
+/// environment's own distribution and weighted against BSDF sampling.
+fn sample_environment<T>(ray: &Ray, rec: &HitRecord, outside_ior: f64, world: &T, environment: &Environment, channels: &Channels, sampler: &mut Sampler) -> Color
+where T: Hittable
+{
+    let (direction, light, light_pdf) = match environment.sample(sampler) {
+        Some(sample) => sample,
+        None => return Color::default(),
+    };
+
+    let f = rec.material.eval(ray, rec, outside_ior, channels, &direction);
+    if f.near_zero() {
+        return Color::default();
+    }
+
+    let visibility = world.transmittance(&Ray::new(rec.p, direction), 0.001, INFINITY);
+    if visibility == 0.0 {
+        return Color::default();
+    }
+
+    let weight = power_heuristic(light_pdf, rec.material.pdf(ray, rec, outside_ior, channels, &direction));
+    f * channels.lift(light) * (visibility * weight / light_pdf)
+}
+
+/// Direct lighting at `rec` from the scene's punctual lights.
+/// These can't be hit by chance, so there's nothing to weight against.
+fn sample_lights<T>(ray: &Ray, rec: &HitRecord, outside_ior: f64, scene: &Scene<T>, channels: &Channels) -> Color
+where T: Hittable
+{
+    let mut direct = Color::default();
+
+    for light in &scene.lights {
+        let (direction, light, distance) = light.sample(&rec.p);
+
+        let f = rec.material.eval(ray, rec, outside_ior, channels, &direction);
+        if f.near_zero() {
+            continue;
+        }
+
+        let visibility = scene.world.transmittance(&Ray::new(rec.p, direction), 0.001, distance - 0.001);
+        if visibility == 0.0 {
+            continue;
         }
 
-        return Color::new(0.0, 0.0, 0.0);
+        direct += f * channels.lift(light) * visibility;
     }
 
-    let unit_direction: Vec3 = ray.direction.unit_vector();
-    let t = 0.5 * (unit_direction.y + 1.0);
-    ((1.0 - t) * Color::new(1.0, 1.0, 1.0)) + (t * Color::new(0.5, 0.7, 1.0))
+    direct
+}
+
+/// Direct lighting at `rec` from one of the scene's emissive objects,
+/// picked uniformly and weighted against BSDF sampling.
+fn sample_area_lights<T>(ray: &Ray, rec: &HitRecord, outside_ior: f64, scene: &Scene<T>, channels: &Channels, sampler: &mut Sampler) -> Color
+where T: Hittable
+{
+    if scene.area_lights.is_empty() {
+        return Color::default();
+    }
+
+    let index = ((sampler.get_1d() * scene.area_lights.len() as f64) as usize).min(scene.area_lights.len() - 1);
+    let direction = scene.area_lights[index].random(&rec.p, sampler.get_2d());
+
+    let light_pdf = area_light_pdf(scene, &rec.p, &direction);
+    if light_pdf <= 0.0 {
+        return Color::default();
+    }
+
+    let f = rec.material.eval(ray, rec, outside_ior, channels, &direction);
+    if f.near_zero() {
+        return Color::default();
+    }
+
+    // Whatever is hit first is what we see, including other lights.
+    let mut light_rec = HitRecord::default();
+    if !scene.world.hit(&Ray::new(rec.p, direction), 0.001, INFINITY, &mut light_rec) {
+        return Color::default();
+    }
+
+    let light = light_rec.material.emitted(&light_rec);
+    let weight = power_heuristic(light_pdf, rec.material.pdf(ray, rec, outside_ior, channels, &direction));
+    f * channels.lift(light) * (weight / light_pdf)
+}
+
+/// Solid angle PDF of `sample_area_lights` choosing `direction` from `origin`.
+fn area_light_pdf<T>(scene: &Scene<T>, origin: &Point3, direction: &Vec3) -> f64
+where T: Hittable
+{
+    if scene.area_lights.is_empty() {
+        return 0.0;
+    }
+
+    let sum: f64 = scene.area_lights.iter().map(|light| light.pdf_value(origin, direction)).sum();
+    sum / scene.area_lights.len() as f64
+}
+
+/// Multiple importance sampling weight for a sample drawn with `pdf_f` when
+/// it could also have been drawn with `pdf_g`.
+fn power_heuristic(pdf_f: f64, pdf_g: f64) -> f64 {
+    let f = pdf_f * pdf_f;
+    let g = pdf_g * pdf_g;
+    f / (f + g)
+}
+
+/// CIE XYZ to linear sRGB (D65 white point), by rows.
+pub const SRGB_FROM_XYZ: [[f64; 3]; 3] = [
+    [3.2406, -1.5372, -0.4986],
+    [-0.9689, 1.8758, 0.0415],
+    [0.0557, -0.2040, 1.0570],
+];
+
+/// CIE XYZ to linear sRGB, clamping colors outside the gamut.
+pub fn xyz_to_linear_srgb(xyz: &Vec3) -> Color {
+    let row = |r: [f64; 3]| (r[0] * xyz.x + r[1] * xyz.y + r[2] * xyz.z).max(0.0);
+    Color::new(row(SRGB_FROM_XYZ[0]), row(SRGB_FROM_XYZ[1]), row(SRGB_FROM_XYZ[2]))
 }
 
 pub fn make_color(point: Point3, samples_per_pixel: u32) -> Color32 {
+    // Spectral sums can stray just below zero, outside the gamut.
     let scale = 1.0 / samples_per_pixel as f64;
 
-    let r = (256.0 * clamp((point.x * scale).sqrt(), 0.0, 0.999)) as i64;
-    let g = (256.0 * clamp((point.y * scale).sqrt(), 0.0, 0.999)) as i64;
-    let b = (256.0 * clamp((point.z * scale).sqrt(), 0.0, 0.999)) as i64;
+    let r = (256.0 * clamp((point.x * scale).max(0.0).sqrt(), 0.0, 0.999)) as i64;
+    let g = (256.0 * clamp((point.y * scale).max(0.0).sqrt(), 0.0, 0.999)) as i64;
+    let b = (256.0 * clamp((point.z * scale).max(0.0).sqrt(), 0.0, 0.999)) as i64;
 
 
     (b << (8 * 2) | g << (8 * 1) | r << (8 * 0)) as Color32
@@ -0,0 +1,229 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vector::Point3;
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CsgOperation {
+    /// Inside either.
+    Union,
+
+    /// Inside both.
+    Intersection,
+
+    /// Inside the left, but not the right.
+    Difference,
+}
+
+impl CsgOperation {
+    fn inside(self: Self, inside_left: bool, inside_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => inside_left || inside_right,
+            CsgOperation::Intersection => inside_left && inside_right,
+            CsgOperation::Difference => inside_left && !inside_right,
+        }
+    }
+}
+
+
+/// Constructive solid geometry over two closed objects.
+/// Surfaces carved out by the right side of a `Difference` keep its material.
+pub struct Csg<A, B> where A: Hittable, B: Hittable {
+    left: A,
+    right: B,
+    operation: CsgOperation,
+}
+
+impl<A: Hittable, B: Hittable> Csg<A, B> {
+    pub fn new(left: A, right: B, operation: CsgOperation) -> Self {
+        Csg { left, right, operation }
+    }
+
+    pub fn union(left: A, right: B) -> Self {
+        Csg::new(left, right, CsgOperation::Union)
+    }
+
+    pub fn intersection(left: A, right: B) -> Self {
+        Csg::new(left, right, CsgOperation::Intersection)
+    }
+
+    pub fn difference(left: A, right: B) -> Self {
+        Csg::new(left, right, CsgOperation::Difference)
+    }
+}
+
+impl<A: Hittable, B: Hittable> Hittable for Csg<A, B> {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        if let Some(bbox) = self.bounding_box() {
+            if !bbox.hit(ray, t_min, t_max) {
+                return false;
+            }
+        }
+
+        let mut hits = vec![];
+        self.hit_all(ray, &mut hits);
+
+        match hits.into_iter().find(|h| h.t >= t_min && h.t <= t_max) {
+            Some(hit) => { *rec = hit; true }
+            None => false,
+        }
+    }
+
+    fn hit_all(self: &Self, ray: &Ray, hits: &mut Vec<HitRecord>) {
+        let mut left_hits = vec![];
+        let mut right_hits = vec![];
+        self.left.hit_all(ray, &mut left_hits);
+        self.right.hit_all(ray, &mut right_hits);
+
+        // Both lists start far behind the ray, outside their object, unless
+        // the first crossing is an exit.
+        let mut inside_left = starts_inside(&self.left, &left_hits, ray);
+        let mut inside_right = starts_inside(&self.right, &right_hits, ray);
+        let mut inside = self.operation.inside(inside_left, inside_right);
+
+        let (mut i, mut j) = (0, 0);
+        while i < left_hits.len() || j < right_hits.len() {
+            let from_left = j >= right_hits.len() || (i < left_hits.len() && left_hits[i].t <= right_hits[j].t);
+
+            let mut hit = match from_left {
+                true => { i += 1; left_hits[i - 1].clone() }
+                false => { j += 1; right_hits[j - 1].clone() }
+            };
+
+            match from_left {
+                true => inside_left = hit.front_face,
+                false => inside_right = hit.front_face,
+            }
+
+            let now_inside = self.operation.inside(inside_left, inside_right);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+
+            // The boundary of what's carved out faces the other way.
+            let outward_normal = if hit.front_face { hit.normal } else { -hit.normal };
+            let outward_normal = match !from_left && self.operation == CsgOperation::Difference {
+                true => -outward_normal,
+                false => outward_normal,
+            };
+            hit.set_face_normal(ray, &outward_normal);
+
+            hits.push(hit);
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        let left = self.left.bounding_box();
+        let right = self.right.bounding_box();
+
+        match self.operation {
+            CsgOperation::Union => Some(Aabb::surrounding_box(&left?, &right?)),
+            CsgOperation::Intersection => match (left, right) {
+                (Some(l), Some(r)) => Some(Aabb::new(
+                    Point3::new(l.minimum.x.max(r.minimum.x), l.minimum.y.max(r.minimum.y), l.minimum.z.max(r.minimum.z)),
+                    Point3::new(l.maximum.x.min(r.maximum.x), l.maximum.y.min(r.maximum.y), l.maximum.z.min(r.maximum.z)),
+                )),
+                (Some(b), None) | (None, Some(b)) => Some(b),
+                (None, None) => None,
+            },
+            CsgOperation::Difference => left,
+        }
+    }
+}
+
+/// Whether the whole line of `ray` starts inside `object`, from its
+/// crossings along it. A bounded object the line never crosses is outside
+/// all along, but an unbounded one, such as a plane's half-space running
+/// alongside the ray, may be inside, so look across the ray for it instead.
+fn starts_inside<T: Hittable>(object: &T, hits: &[HitRecord], ray: &Ray) -> bool {
+    if let Some(first) = hits.first() {
+        return !first.front_face;
+    }
+    if object.bounding_box().is_some() {
+        return false;
+    }
+
+    // Any plane along the ray is crossed by at least one of these.
+    let frame = Onb::build_from_w(&ray.direction);
+    for across in [frame.u, frame.v] {
+        let mut hits = vec![];
+        object.hit_all(&Ray::new(ray.origin, across), &mut hits);
+        if let Some(next) = hits.iter().find(|h| h.t > 0.0) {
+            return !next.front_face;
+        }
+        if let Some(last) = hits.last() {
+            return last.front_face;
+        }
+    }
+    false
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{Material, MaterialType};
+    use crate::plane::Plane;
+    use crate::sphere::Sphere;
+    use crate::vector::{Color, Vec3};
+
+    /// `(t, front_face)` of each crossing of the x axis through two unit
+    /// spheres at `x = 0` and `x = 1`, from `x = -5`.
+    fn crossings(operation: CsgOperation) -> Vec<(f64, bool)> {
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+        let left = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material.clone());
+        let right = Sphere::new(Point3::new(1.0, 0.0, 0.0), 1.0, material);
+        let csg = Csg::new(left, right, operation);
+
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut hits = vec![];
+        csg.hit_all(&ray, &mut hits);
+
+        // `hit` agrees with the first crossing.
+        let mut rec = HitRecord::default();
+        assert!(csg.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert_eq!(rec.t, hits[0].t);
+
+        hits.iter().map(|h| ((h.t * 1e6).round() / 1e6, h.front_face)).collect()
+    }
+
+    #[test]
+    fn test_union() {
+        assert_eq!(crossings(CsgOperation::Union), vec![(4.0, true), (7.0, false)]);
+    }
+
+    #[test]
+    fn test_intersection() {
+        assert_eq!(crossings(CsgOperation::Intersection), vec![(5.0, true), (6.0, false)]);
+    }
+
+    #[test]
+    fn test_difference() {
+        assert_eq!(crossings(CsgOperation::Difference), vec![(4.0, true), (5.0, false)]);
+    }
+
+    #[test]
+    fn test_half_space_along_the_ray() {
+        // A unit sphere cut by the plane y = 0.5, which the rays run along,
+        // one below it and one above.
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+        let sphere = || Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material.clone());
+        let below = || Plane::new(Point3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0), material.clone());
+        let ts = |csg: &dyn Hittable, y: f64| {
+            let mut hits = vec![];
+            csg.hit_all(&Ray::new(Point3::new(-5.0, y, 0.0), Vec3::new(1.0, 0.0, 0.0)), &mut hits);
+            hits.iter().map(|h| ((h.t * 1e6).round() / 1e6, h.front_face)).collect::<Vec<_>>()
+        };
+
+        let intersection = Csg::intersection(sphere(), below());
+        assert_eq!(ts(&intersection, 0.0), vec![(4.0, true), (6.0, false)]);
+        assert_eq!(ts(&intersection, 0.6), vec![]);
+
+        let difference = Csg::difference(sphere(), below());
+        assert_eq!(ts(&difference, 0.0), vec![]);
+        assert_eq!(ts(&difference, 0.6).len(), 2);
+    }
+}
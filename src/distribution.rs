@@ -0,0 +1,134 @@
+// Piecewise-constant distributions, used to importance sample tabulated
+// functions such as environment maps.
+
+
+/// A piecewise-constant 1-D distribution over `[0, 1)`.
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    func_int: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: &[f64]) -> Self {
+        let n = func.len();
+        assert!(n > 0, "Can't build a distribution over nothing");
+
+        let func: Vec<f64> = func.iter().map(|f| f.abs()).collect();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as f64;
+        }
+
+        let func_int = cdf[n];
+        if func_int == 0.0 {
+            // Nothing to prefer, so fall back to uniform sampling.
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as f64 / n as f64;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= func_int;
+            }
+        }
+
+        Distribution1D { func, cdf, func_int }
+    }
+
+    pub fn count(self: &Self) -> usize {
+        self.func.len()
+    }
+
+    /// The integral of the function over `[0, 1)`.
+    pub fn integral(self: &Self) -> f64 {
+        self.func_int
+    }
+
+    /// Map a uniform `u` in `[0, 1)` to `(x, pdf, offset)`, where `offset` is
+    /// the index of the segment `x` landed in.
+    pub fn sample_continuous(self: &Self, u: f64) -> (f64, f64, usize) {
+        // Last index whose CDF value is <= u.
+        let offset = self.cdf.partition_point(|&c| c <= u).saturating_sub(1).min(self.count() - 1);
+
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        let x = (offset as f64 + du) / self.count() as f64;
+        (x, self.pdf(offset), offset)
+    }
+
+    /// Density of the segment at `offset`.
+    pub fn pdf(self: &Self, offset: usize) -> f64 {
+        if self.func_int == 0.0 {
+            return 1.0;
+        }
+        self.func[offset] / self.func_int
+    }
+}
+
+
+/// A piecewise-constant 2-D distribution over `[0, 1)^2`, built from a
+/// row-major grid. Rows are indexed by `v` and columns by `u`.
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        assert_eq!(func.len(), width * height);
+
+        let conditional: Vec<Distribution1D> = func
+            .chunks(width)
+            .map(Distribution1D::new)
+            .collect();
+        let marginal_func: Vec<f64> = conditional.iter().map(|d| d.integral()).collect();
+
+        Distribution2D {
+            conditional,
+            marginal: Distribution1D::new(&marginal_func),
+        }
+    }
+
+    /// Map two uniform numbers to `((u, v), pdf)`.
+    pub fn sample_continuous(self: &Self, u0: f64, u1: f64) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u0);
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    pub fn pdf(self: &Self, u: f64, v: f64) -> f64 {
+        let row = ((v * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        let conditional = &self.conditional[row];
+        let column = ((u * conditional.count() as f64) as usize).min(conditional.count() - 1);
+
+        conditional.pdf(column) * self.marginal.pdf(row)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_matches_pdf() {
+        let func = [1.0, 0.0, 3.0, 4.0, 2.0, 2.0];
+        let distribution = Distribution2D::new(&func, 3, 2);
+
+        // The second row holds two thirds of the mass, so it takes u1 >= 1/3.
+        let ((u, v), pdf) = distribution.sample_continuous(0.1, 0.6);
+        assert!(v >= 0.5);
+        assert!((pdf - distribution.pdf(u, v)).abs() < 1e-12);
+
+        // The zero-valued texel is never chosen.
+        for i in 0..100 {
+            let ((u, v), _) = distribution.sample_continuous(i as f64 / 100.0, 0.25);
+            assert!(v < 0.5);
+            assert!(!(1.0 / 3.0..2.0 / 3.0).contains(&u));
+        }
+    }
+}
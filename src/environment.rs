@@ -0,0 +1,176 @@
+use std::error;
+use std::f64::consts::PI;
+
+use crate::distribution::Distribution2D;
+use crate::image_reader::read_float_image;
+use crate::sky::PhysicalSky;
+use crate::sampler::Sampler;
+use crate::utility::degrees_to_radians;
+use crate::vector::{Color, Vec3};
+
+/// Let's Box any errors!
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+
+/// What a ray sees when it escapes the world without hitting anything.
+pub enum Environment {
+    /// The same radiance in every direction.
+    Constant(Color),
+
+    /// Gradient(bottom, top), blended on the `y` component of the direction.
+    Gradient(Color, Color),
+
+    /// An equirectangular HDR image.
+    Map(EnvironmentMap),
+
+    /// An analytic daylight sky with a sun disk.
+    Sky(PhysicalSky),
+}
+
+impl Environment {
+    /// The white-to-blue sky we've always had.
+    pub fn default() -> Self {
+        Environment::Gradient(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+
+    /// Radiance arriving from `direction`.
+    pub fn value(self: &Self, direction: &Vec3) -> Color {
+        match self {
+            Environment::Constant(c) => *c,
+            Environment::Gradient(bottom, top) => {
+                let unit_direction: Vec3 = direction.unit_vector();
+                let t = 0.5 * (unit_direction.y + 1.0);
+                ((1.0 - t) * *bottom) + (t * *top)
+            }
+            Environment::Map(map) => map.value(direction),
+            Environment::Sky(sky) => sky.value(direction),
+        }
+    }
+
+    /// Pick a direction to treat the environment as a light.
+    /// Returns `(direction, radiance, pdf)` where the PDF is over solid angle,
+    /// or `None` if this environment can't be importance sampled.
+    pub fn sample(self: &Self, sampler: &mut Sampler) -> Option<(Vec3, Color, f64)> {
+        match self {
+            Environment::Map(map) => {
+                let (u1, u2) = sampler.get_2d();
+                map.sample(u1, u2)
+            }
+            Environment::Sky(sky) => {
+                let ((u0, u1), u2) = (sampler.get_2d(), sampler.get_1d());
+                sky.sample(u0, u1, u2)
+            }
+            _ => None,
+        }
+    }
+
+    /// Solid angle PDF of `sample` returning `direction`.
+    pub fn pdf(self: &Self, direction: &Vec3) -> f64 {
+        match self {
+            Environment::Map(map) => map.pdf(direction),
+            Environment::Sky(sky) => sky.pdf(direction),
+            _ => 0.0,
+        }
+    }
+}
+
+
+/// An equirectangular (latitude-longitude) environment map.
+/// The top row of the image is straight up (`+y`).
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+
+    /// Rotation around the `y` axis, in radians.
+    rotation: f64,
+
+    /// Scale applied to every texel.
+    intensity: f64,
+
+    /// Proportional to luminance times `sin(theta)`, so that texels near the
+    /// poles, which cover less solid angle, are picked less often.
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// `rotation` is in degrees, around the `y` axis.
+    pub fn new(width: usize, height: usize, texels: Vec<Color>, rotation: f64, intensity: f64) -> Self {
+        assert_eq!(texels.len(), width * height);
+
+        let mut func: Vec<f64> = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            func.extend(texels[y * width..(y + 1) * width].iter().map(|t| t.luminance() * sin_theta));
+        }
+
+        EnvironmentMap {
+            width,
+            height,
+            texels,
+            rotation: degrees_to_radians(rotation),
+            intensity,
+            distribution: Distribution2D::new(&func, width, height),
+        }
+    }
+
+    /// Load an environment map from a `.hdr` or `.exr` file.
+    pub fn load(file_path: &str, rotation: f64, intensity: f64) -> Result<Self> {
+        let image = read_float_image(file_path)?;
+        Ok(EnvironmentMap::new(image.width, image.height, image.texels, rotation, intensity))
+    }
+
+    pub fn value(self: &Self, direction: &Vec3) -> Color {
+        let (u, v) = self.direction_to_uv(direction);
+        self.texel(u, v)
+    }
+
+    /// Returns `(direction, radiance, pdf)` for two uniform numbers.
+    pub fn sample(self: &Self, u0: f64, u1: f64) -> Option<(Vec3, Color, f64)> {
+        let ((u, v), map_pdf) = self.distribution.sample_continuous(u0, u1);
+        if map_pdf == 0.0 {
+            return None;
+        }
+
+        let theta = v * PI;
+        let phi = u * 2.0 * PI - self.rotation;
+        let sin_theta = theta.sin();
+        if sin_theta == 0.0 {
+            return None;
+        }
+
+        let direction = Vec3::new(sin_theta * phi.cos(), theta.cos(), sin_theta * phi.sin());
+
+        // Change of variables from (u, v) to solid angle.
+        let pdf = map_pdf / (2.0 * PI * PI * sin_theta);
+        Some((direction, self.texel(u, v), pdf))
+    }
+
+    pub fn pdf(self: &Self, direction: &Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    /// Map a direction to image co-ordinates in `[0, 1)`.
+    fn direction_to_uv(self: &Self, direction: &Vec3) -> (f64, f64) {
+        let d = direction.unit_vector();
+        let theta = d.y.clamp(-1.0, 1.0).acos();
+        let phi = d.z.atan2(d.x) + self.rotation;
+
+        let u = (phi / (2.0 * PI)).rem_euclid(1.0);
+        let v = theta / PI;
+        (u, v)
+    }
+
+    /// Nearest texel lookup.
+    fn texel(self: &Self, u: f64, v: f64) -> Color {
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.intensity * self.texels[y * self.width + x]
+    }
+}
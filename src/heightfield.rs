@@ -0,0 +1,291 @@
+use std::error;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::image_reader::{read_netpbm, FloatImage};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vector::{Point3, Vec3};
+
+/// Let's Box any errors!
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+
+/// Terrain from a regular grid of heights. Each grid cell is split into two
+/// triangles, shaded with normals interpolated from the corners.
+/// Rays walk the grid cell by cell, skipping cells they pass over or under.
+pub struct Heightfield {
+    /// Samples along `x` and `z`. Sample `(i, j)` is at `heights[j * nx + i]`.
+    nx: usize,
+    nz: usize,
+    heights: Vec<f64>,
+
+    /// Per sample normals, by central differences.
+    normals: Vec<Vec3>,
+
+    /// Lowest and highest corner of each cell, `(nx - 1) * (nz - 1)` of them.
+    cell_ranges: Vec<(f64, f64)>,
+
+    corner: Point3,
+    cell_x: f64,
+    cell_z: f64,
+    bounds: Aabb,
+    material: Material,
+}
+
+impl Heightfield {
+    /// `heights` are in `[0, 1]`, scaled by `size.y`. The grid covers
+    /// `size.x` by `size.z` starting from `corner`.
+    pub fn new(nx: usize, nz: usize, heights: Vec<f64>, corner: Point3, size: Vec3, material: Material) -> Self {
+        assert!(nx >= 2 && nz >= 2, "A heightfield needs at least 2x2 samples");
+        assert_eq!(heights.len(), nx * nz);
+
+        let heights: Vec<f64> = heights.iter().map(|h| corner.y + h * size.y).collect();
+        let cell_x = size.x / (nx - 1) as f64;
+        let cell_z = size.z / (nz - 1) as f64;
+
+        let height_at = |i: usize, j: usize| heights[j * nx + i];
+
+        let mut normals = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nx - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(nz - 1));
+                let dh_dx = (height_at(i1, j) - height_at(i0, j)) / ((i1 - i0) as f64 * cell_x);
+                let dh_dz = (height_at(i, j1) - height_at(i, j0)) / ((j1 - j0) as f64 * cell_z);
+                normals.push(Vec3::new(-dh_dx, 1.0, -dh_dz).unit_vector());
+            }
+        }
+
+        let mut cell_ranges = Vec::with_capacity((nx - 1) * (nz - 1));
+        for j in 0..nz - 1 {
+            for i in 0..nx - 1 {
+                let corners = [height_at(i, j), height_at(i + 1, j), height_at(i, j + 1), height_at(i + 1, j + 1)];
+                let low = corners.iter().cloned().fold(f64::INFINITY, f64::min);
+                let high = corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                cell_ranges.push((low, high));
+            }
+        }
+
+        let low = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+        let high = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let bounds = Aabb::new(
+            Point3::new(corner.x, low, corner.z),
+            Point3::new(corner.x + size.x, high, corner.z + size.z),
+        ).pad(0.0001);
+
+        Heightfield {
+            nx,
+            nz,
+            heights,
+            normals,
+            cell_ranges,
+            corner,
+            cell_x,
+            cell_z,
+            bounds,
+            material,
+        }
+    }
+
+    /// Heights from the brightness of an image, with its top row at `corner.z`.
+    pub fn from_image(image: &FloatImage, corner: Point3, size: Vec3, material: Material) -> Self {
+        let heights = image.texels.iter().map(|t| t.luminance()).collect();
+        Heightfield::new(image.width, image.height, heights, corner, size, material)
+    }
+
+    /// Load heights from a greyscale `.pgm` (or colour `.ppm`) image.
+    pub fn load(file_path: &str, corner: Point3, size: Vec3, material: Material) -> Result<Self> {
+        Ok(Heightfield::from_image(&read_netpbm(file_path)?, corner, size, material))
+    }
+
+    fn vertex(self: &Self, i: usize, j: usize) -> Point3 {
+        Point3::new(
+            self.corner.x + i as f64 * self.cell_x,
+            self.heights[j * self.nx + i],
+            self.corner.z + j as f64 * self.cell_z,
+        )
+    }
+
+    /// Test both triangles of cell `(i, j)`, keeping the nearest hit before `t_max`.
+    fn hit_cell(self: &Self, ray: &Ray, i: usize, j: usize, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let mut closest = t_max;
+        let mut hit = None;
+
+        for triangle in [[0, 1, 2], [0, 2, 3]] {
+            let [a, b, c] = triangle.map(|k| corners[k]);
+            if let Some((t, beta, gamma)) = intersect_triangle(ray, self.vertex(a.0, a.1), self.vertex(b.0, b.1), self.vertex(c.0, c.1)) {
+                if t >= t_min && t <= closest {
+                    closest = t;
+                    hit = Some((t, [a, b, c], beta, gamma));
+                }
+            }
+        }
+
+        let (t, [a, b, c], beta, gamma) = match hit {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        rec.t = t;
+        rec.p = ray.at(t);
+
+        let normal_at = |(i, j): (usize, usize)| self.normals[j * self.nx + i];
+        let outward_normal = ((1.0 - beta - gamma) * normal_at(a) + beta * normal_at(b) + gamma * normal_at(c)).unit_vector();
+        rec.set_face_normal(ray, &outward_normal);
+
+        rec.u = (rec.p.x - self.corner.x) / (self.cell_x * (self.nx - 1) as f64);
+        rec.v = (rec.p.z - self.corner.z) / (self.cell_z * (self.nz - 1) as f64);
+
+        // Along the smooth surface the interpolated normal belongs to, with
+        // its slopes in x and z, so bumps tilt that rather than the flat.
+        let (dh_dx, dh_dz) = (-outward_normal.x / outward_normal.y, -outward_normal.z / outward_normal.y);
+        rec.dpdu = self.cell_x * (self.nx - 1) as f64 * Vec3::new(1.0, dh_dx, 0.0);
+        rec.dpdv = self.cell_z * (self.nz - 1) as f64 * Vec3::new(0.0, dh_dz, 1.0);
+        rec.material = self.material.clone();
+
+        true
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let (t_enter, t_exit) = match self.bounds.hit_interval(ray, t_min, t_max) {
+            Some(interval) => interval,
+            None => return false,
+        };
+
+        // Grid DDA over the cells, in the xz plane.
+        let start = ray.at(t_enter);
+        let cells_x = self.nx - 1;
+        let cells_z = self.nz - 1;
+        let to_cell = |x: f64, cell: f64, cells: usize| (((x / cell).floor()) as i64).clamp(0, cells as i64 - 1);
+
+        let mut i = to_cell(start.x - self.corner.x, self.cell_x, cells_x);
+        let mut j = to_cell(start.z - self.corner.z, self.cell_z, cells_z);
+
+        let step_i: i64 = if ray.direction.x > 0.0 { 1 } else { -1 };
+        let step_j: i64 = if ray.direction.z > 0.0 { 1 } else { -1 };
+
+        // Ray parameter at which the next cell boundary is crossed, and
+        // between successive boundaries, along each axis.
+        let boundary = |index: i64, step: i64, cell: f64, origin: f64, start: f64, direction: f64| {
+            if direction == 0.0 {
+                return (f64::INFINITY, f64::INFINITY);
+            }
+            let next = origin + (index + if step > 0 { 1 } else { 0 }) as f64 * cell;
+            ((next - start) / direction, cell / direction.abs())
+        };
+        let (mut next_x, delta_x) = boundary(i, step_i, self.cell_x, self.corner.x, ray.origin.x, ray.direction.x);
+        let (mut next_z, delta_z) = boundary(j, step_j, self.cell_z, self.corner.z, ray.origin.z, ray.direction.z);
+
+        let mut t_cell = t_enter;
+        loop {
+            let t_leave = next_x.min(next_z).min(t_exit);
+
+            // Only look at the triangles if the ray's height over the cell
+            // overlaps the cell's heights.
+            let (low, high) = self.cell_ranges[j as usize * cells_x + i as usize];
+            let y0 = ray.at(t_cell).y;
+            let y1 = ray.at(t_leave).y;
+            if y0.min(y1) <= high && y0.max(y1) >= low
+                && self.hit_cell(ray, i as usize, j as usize, t_min, t_max, rec) {
+                return true;
+            }
+
+            if t_leave >= t_exit {
+                return false;
+            }
+
+            if next_x < next_z {
+                i += step_i;
+                next_x += delta_x;
+            } else {
+                j += step_j;
+                next_z += delta_z;
+            }
+            if i < 0 || j < 0 || i >= cells_x as i64 || j >= cells_z as i64 {
+                return false;
+            }
+            t_cell = t_leave;
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+/// Möller–Trumbore ray/triangle intersection, returning `t` and the
+/// barycentric weights of `b` and `c`.
+fn intersect_triangle(ray: &Ray, a: Point3, b: Point3, c: Point3) -> Option<(f64, f64, f64)> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(edge2);
+    let determinant = edge1.dot(&p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let s = ray.origin - a;
+    let beta = s.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&beta) {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let gamma = ray.direction.dot(&q) * inverse;
+    if gamma < 0.0 || beta + gamma > 1.0 {
+        return None;
+    }
+
+    Some((edge2.dot(&q) * inverse, beta, gamma))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bump::Bump;
+    use crate::material::MaterialType;
+    use crate::texture::Texture;
+    use crate::vector::Color;
+
+    #[test]
+    fn test_hit_sloped_heightfield() {
+        // A ramp rising one unit over four along x.
+        let heights = vec![0.0, 0.25, 0.5, 0.75, 1.0].repeat(3);
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+        let field = Heightfield::new(5, 3, heights, Point3::new(0.0, 0.0, 0.0), Vec3::new(4.0, 1.0, 2.0), material);
+
+        let mut rec = HitRecord::default();
+        let ray = Ray::new(Point3::new(2.5, 5.0, 1.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(field.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert!((rec.p.y - 0.625).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(-0.25, 1.0, 0.0).unit_vector()).length() < 1e-9);
+
+        // Skimming along the ramp, just above it, misses.
+        let ray = Ray::new(Point3::new(-1.0, -0.2, 1.0), Vec3::new(1.0, 0.25, 0.0));
+        assert!(!field.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+    }
+
+    #[test]
+    fn test_flat_bump_keeps_the_slope() {
+        // Rising along x and falling along z, so both tangents tilt.
+        let heights: Vec<f64> = (0..4).flat_map(|j| (0..5).map(move |i| 0.1 * i as f64 - 0.05 * j as f64 + 0.2)).collect();
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+        let field = Heightfield::new(5, 4, heights, Point3::new(0.0, 0.0, 0.0), Vec3::new(4.0, 2.0, 3.0), material);
+
+        for direction in [Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.3, -1.0, -0.2)] {
+            let mut rec = HitRecord::default();
+            assert!(field.hit(&Ray::new(Point3::new(2.2, 5.0, 1.3), direction), 0.001, f64::INFINITY, &mut rec));
+            let normal = rec.normal;
+            assert!(normal.x < -0.1 && normal.z > 0.05);
+
+            Bump::Height(Texture::Constant(Color::new(0.6, 0.6, 0.6)), 1.0).apply(&mut rec, &direction);
+            assert!((rec.normal - normal).length() < 1e-6, "{} != {}", rec.normal, normal);
+        }
+    }
+}
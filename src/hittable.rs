@@ -1,13 +1,27 @@
+use crate::aabb::Aabb;
 use crate::material::{Material, MaterialType};
 use crate::vector::{Vec3, Point3, Color};
 use crate::ray::Ray;
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
     pub material: Material,
     pub t: f64,
+
+    /// Surface co-ordinates of the hit, each in `[0, 1]`.
+    pub u: f64,
+    pub v: f64,
+
+    /// How `p` moves with `u` and `v`, for bump mapping. Zero where the
+    /// shape doesn't say.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+
+    /// The normal from the geometry, where a bump map has tilted `normal`.
+    pub geometric_normal: Option<Vec3>,
+
     pub front_face: bool,
 }
 
@@ -18,6 +32,11 @@ impl HitRecord {
             normal: Vec3::default(), 
             material: Material::new(MaterialType::Lambertian(Color::default())),
             t: 0.0, 
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
+            geometric_normal: None,
             front_face: false 
         }
     }
@@ -29,10 +48,140 @@ impl HitRecord {
             false => -*outward_normal
         }
     }
+
+    /// Whether `direction` is on the same side of the shading normal as of
+    /// the geometry. Where a bump map makes them disagree, light would leak
+    /// through the surface, or be blocked by nothing, so none goes that way.
+    pub fn sides_agree(self: &Self, direction: &Vec3) -> bool {
+        match self.geometric_normal {
+            Some(geometric_normal) => (direction.dot(&geometric_normal) > 0.0) == (direction.dot(&self.normal) > 0.0),
+            None => true,
+        }
+    }
 }
 
 pub trait Hittable {
     fn hit(self: &Self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+
+    /// `None` for objects without bounds.
+    fn bounding_box(self: &Self) -> Option<Aabb>;
+
+    /// Solid angle PDF of `random` returning `direction` from `origin`.
+    /// Only objects that can be sampled as lights need this.
+    fn pdf_value(self: &Self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    /// A direction from `origin` towards the point on the object picked by
+    /// `u`, from `[0, 1)²`.
+    fn random(self: &Self, _origin: &Point3, _u: (f64, f64)) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// Every surface crossing along the whole (infinite) line of the ray, in
+    /// order of `t`. `front_face` tells entries from exits.
+    /// The default walks along the ray with repeated calls to `hit`; shapes
+    /// that can do better should.
+    fn hit_all(self: &Self, r: &Ray, hits: &mut Vec<HitRecord>) {
+        let mut rec = HitRecord::default();
+        let mut t_min = -f64::INFINITY;
+
+        while hits.len() < MAX_CROSSINGS && self.hit(r, t_min, f64::INFINITY, &mut rec) {
+            hits.push(rec.clone());
+            t_min = rec.t + 1e-6 * rec.t.abs().max(1.0);
+        }
+    }
+
+    /// Fraction of light getting along the ray from `t_min` to `t_max`, for
+    /// shadow rays. Solid objects let nothing through, except where cut
+    /// out; participating media should do better than the all or nothing of
+    /// a random `hit`.
+    fn transmittance(self: &Self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut rec = HitRecord::default();
+        let mut transmittance = 1.0;
+        let mut t_min = t_min;
+
+        for _ in 0..MAX_CROSSINGS {
+            if !self.hit(r, t_min, t_max, &mut rec) {
+                return transmittance;
+            }
+            transmittance *= 1.0 - rec.material.opacity(&rec);
+            if transmittance == 0.0 {
+                break;
+            }
+            t_min = rec.t + 1e-6 * rec.t.abs().max(1.0);
+        }
+        0.0
+    }
+}
+
+/// `object.hit`, carrying on through places where the material is cut out.
+/// Where it's only partly there, the ray gets through as often as it isn't,
+/// decided by hashing the ray and point so that asking again about the same
+/// hit gives the same answer. `rec` is only written for a hit that counts.
+pub fn hit_opaque<T>(object: &T, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool
+where T: Hittable + ?Sized
+{
+    let mut temp_rec = HitRecord::default();
+    let mut t_min = t_min;
+
+    for _ in 0..MAX_CROSSINGS {
+        if !object.hit(r, t_min, t_max, &mut temp_rec) {
+            return false;
+        }
+
+        let opacity = temp_rec.material.opacity(&temp_rec);
+        if opacity >= 1.0 || (opacity > 0.0 && hash(r, &temp_rec.p) < opacity) {
+            *rec = temp_rec.clone();
+            return true;
+        }
+        t_min = temp_rec.t + 1e-6 * temp_rec.t.abs().max(1.0);
+    }
+    false
+}
+
+/// A number in `[0, 1)` that looks random but depends only on the ray and
+/// the point.
+fn hash(r: &Ray, p: &Point3) -> f64 {
+    let mut h: u64 = 0x9e3779b97f4a7c15;
+    for x in [r.origin.x, r.origin.y, r.origin.z, r.direction.x, r.direction.y, r.direction.z, p.x, p.y, p.z] {
+        // SplitMix64's finalizer, folding in each co-ordinate's bits.
+        h ^= x.to_bits();
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+        h ^= h >> 31;
+    }
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Upper bound on what `hit_all` reports, in case a shape keeps finding the
+/// same crossing.
+const MAX_CROSSINGS: usize = 64;
+
+impl<T: Hittable + ?Sized> Hittable for Box<T> {
+    fn hit(self: &Self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        (**self).hit(r, t_min, t_max, rec)
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(self: &Self, origin: &Point3, direction: &Vec3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(self: &Self, origin: &Point3, u: (f64, f64)) -> Vec3 {
+        (**self).random(origin, u)
+    }
+
+    fn hit_all(self: &Self, r: &Ray, hits: &mut Vec<HitRecord>) {
+        (**self).hit_all(r, hits)
+    }
+
+    fn transmittance(self: &Self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        (**self).transmittance(r, t_min, t_max)
+    }
 }
 
 
@@ -64,13 +213,38 @@ impl<T: Hittable> Hittable for Hittables<T> {
         let mut closest_so_far = t_max;
 
         for object in &self.objects {
-            if object.hit(r, t_min, closest_so_far, &mut temp_rec) {
+            if hit_opaque(object, r, t_min, closest_so_far, &mut temp_rec) {
                 hit_once = true;
                 closest_so_far = temp_rec.t;
-                *rec = temp_rec;
+                *rec = temp_rec.clone();
             }
         }
 
         return hit_once;
     }
+
+    fn transmittance(self: &Self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = 1.0;
+        for object in &self.objects {
+            transmittance *= object.transmittance(r, t_min, t_max);
+            if transmittance == 0.0 {
+                break;
+            }
+        }
+        transmittance
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        let mut output_box: Option<Aabb> = None;
+
+        for object in &self.objects {
+            let object_box = object.bounding_box()?;
+            output_box = match output_box {
+                Some(b) => Some(Aabb::surrounding_box(&b, &object_box)),
+                None => Some(object_box),
+            };
+        }
+
+        output_box
+    }
 }
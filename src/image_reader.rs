@@ -0,0 +1,285 @@
+use std::error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::vector::Color;
+
+/// Let's Box any errors!
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+
+/// Linear, floating point image data as read from disk.
+/// Index `texels` as `texels[y * width + x]`, with `y = 0` being the top row.
+#[derive(PartialEq)]
+pub struct FloatImage {
+    pub width: usize,
+    pub height: usize,
+    pub texels: Vec<Color>,
+}
+
+/// Read an HDR image, picking the decoder from the file extension.
+/// Supports Radiance `.hdr` and OpenEXR `.exr` files.
+pub fn read_float_image(file_path: &str) -> Result<FloatImage> {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("hdr") | Some("pic") => read_hdr(file_path),
+        Some("exr") => read_exr(file_path),
+        _ => Err(format!("Unsupported HDR image format: {}", file_path).into()),
+    }
+}
+
+/// Read a Radiance RGBE (`.hdr`) file.
+/// Both flat and new-style run length encoded scanlines are handled.
+pub fn read_hdr(file_path: &str) -> Result<FloatImage> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+    parse_hdr(&mut reader)
+}
+
+fn parse_hdr<R: BufRead>(reader: &mut R) -> Result<FloatImage> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err("Not a Radiance HDR file".into());
+    }
+
+    // Header lines run until the first empty line.
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err("Unexpected end of HDR header".into());
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            break;
+        }
+        if let Some(format) = trimmed.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(format!("Unsupported HDR pixel format: {}", format).into());
+            }
+        }
+    }
+
+    // Only the standard orientation, "-Y height +X width", is supported.
+    line.clear();
+    reader.read_line(&mut line)?;
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() != 4 || tokens[0] != "-Y" || tokens[2] != "+X" {
+        return Err(format!("Unsupported HDR resolution line: {}", line.trim()).into());
+    }
+    let height: usize = tokens[1].parse()?;
+    let width: usize = tokens[3].parse()?;
+
+    let mut texels: Vec<Color> = Vec::with_capacity(width * height);
+    let mut scanline: Vec<[u8; 4]> = vec![[0; 4]; width];
+
+    for _ in 0..height {
+        read_hdr_scanline(reader, &mut scanline)?;
+        texels.extend(scanline.iter().map(rgbe_to_color));
+    }
+
+    Ok(FloatImage { width, height, texels })
+}
+
+fn read_hdr_scanline<R: Read>(reader: &mut R, scanline: &mut [[u8; 4]]) -> Result<()> {
+    let width = scanline.len();
+    let mut first = [0_u8; 4];
+    reader.read_exact(&mut first)?;
+
+    // New-style RLE scanlines start with (2, 2, width_hi, width_lo).
+    let is_rle = (8..0x8000).contains(&width)
+        && first[0] == 2 && first[1] == 2
+        && ((first[2] as usize) << 8 | first[3] as usize) == width;
+
+    if !is_rle {
+        scanline[0] = first;
+        for texel in scanline.iter_mut().skip(1) {
+            reader.read_exact(texel)?;
+        }
+        return Ok(());
+    }
+
+    // Each of the four components is stored separately, as runs.
+    for component in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0_u8; 1];
+            reader.read_exact(&mut count)?;
+
+            if count[0] > 128 {
+                let run = (count[0] - 128) as usize;
+                let mut value = [0_u8; 1];
+                reader.read_exact(&mut value)?;
+                if x + run > width {
+                    return Err("Bad HDR scanline run".into());
+                }
+                for texel in &mut scanline[x..x + run] {
+                    texel[component] = value[0];
+                }
+                x += run;
+            } else {
+                let run = count[0] as usize;
+                if run == 0 || x + run > width {
+                    return Err("Bad HDR scanline data".into());
+                }
+                let mut values = vec![0_u8; run];
+                reader.read_exact(&mut values)?;
+                for (texel, value) in scanline[x..x + run].iter_mut().zip(values) {
+                    texel[component] = value;
+                }
+                x += run;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn rgbe_to_color(rgbe: &[u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::default();
+    }
+
+    // 2^(e - 128) scales the mantissa, which is stored in 8 bits.
+    let scale = 2.0_f64.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * scale,
+        (rgbe[1] as f64 + 0.5) * scale,
+        (rgbe[2] as f64 + 0.5) * scale,
+    )
+}
+
+/// Read the first RGBA layer of an OpenEXR (`.exr`) file. Alpha is ignored.
+pub fn read_exr(file_path: &str) -> Result<FloatImage> {
+    let image = exr::prelude::read_first_rgba_layer_from_file(
+        file_path,
+        |resolution, _| FloatImage {
+            width: resolution.width(),
+            height: resolution.height(),
+            texels: vec![Color::default(); resolution.width() * resolution.height()],
+        },
+        |image: &mut FloatImage, position, (r, g, b, _a): (f32, f32, f32, f32)| {
+            image.texels[position.y() * image.width + position.x()] = Color::new(r as f64, g as f64, b as f64);
+        },
+    )?;
+
+    Ok(image.layer_data.channel_data.pixels)
+}
+
+
+/// Read a Netpbm greyscale (`.pgm`) or colour (`.ppm`) image, in either the
+/// plain (`P2`, `P3`) or raw (`P5`, `P6`) flavour. Values are scaled to
+/// `[0, 1]` by the file's maximum value, with no gamma applied.
+pub fn read_netpbm(file_path: &str) -> Result<FloatImage> {
+    let mut data = vec![];
+    File::open(file_path)?.read_to_end(&mut data)?;
+    parse_netpbm(&data)
+}
+
+fn parse_netpbm(data: &[u8]) -> Result<FloatImage> {
+    let mut position = 0;
+
+    let magic = next_netpbm_token(data, &mut position)?;
+    let (channels, plain) = match magic {
+        "P2" => (1, true),
+        "P3" => (3, true),
+        "P5" => (1, false),
+        "P6" => (3, false),
+        _ => return Err(format!("Unsupported Netpbm format: {}", magic).into()),
+    };
+
+    let width: usize = next_netpbm_token(data, &mut position)?.parse()?;
+    let height: usize = next_netpbm_token(data, &mut position)?.parse()?;
+    let max_value: u32 = next_netpbm_token(data, &mut position)?.parse()?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(format!("Bad Netpbm maximum value: {}", max_value).into());
+    }
+
+    let count = width * height * channels;
+    let mut values: Vec<f64> = Vec::with_capacity(count);
+
+    if plain {
+        for _ in 0..count {
+            let value: u32 = next_netpbm_token(data, &mut position)?.parse()?;
+            values.push(value as f64 / max_value as f64);
+        }
+    } else {
+        // A single whitespace character separates the header from the raster,
+        // whose samples are one byte each, or two (big endian) past 255.
+        position += 1;
+        let bytes_per_value = if max_value < 256 { 1 } else { 2 };
+        let raster = data.get(position..position + count * bytes_per_value).ok_or("Unexpected end of Netpbm data")?;
+
+        values.extend(raster.chunks_exact(bytes_per_value).map(|sample| {
+            let value = sample.iter().fold(0_u32, |acc, &b| acc << 8 | b as u32);
+            value as f64 / max_value as f64
+        }));
+    }
+
+    let texels = values.chunks_exact(channels)
+        .map(|c| match channels {
+            1 => Color::new(c[0], c[0], c[0]),
+            _ => Color::new(c[0], c[1], c[2]),
+        })
+        .collect();
+
+    Ok(FloatImage { width, height, texels })
+}
+
+/// The next whitespace separated token of a Netpbm header, skipping comments.
+fn next_netpbm_token<'a>(data: &'a [u8], position: &mut usize) -> Result<&'a str> {
+    loop {
+        match data.get(*position) {
+            Some(b'#') => {
+                while data.get(*position).is_some_and(|&b| b != b'\n') {
+                    *position += 1;
+                }
+            }
+            Some(b) if b.is_ascii_whitespace() => *position += 1,
+            Some(_) => break,
+            None => return Err("Unexpected end of Netpbm data".into()),
+        }
+    }
+
+    let start = *position;
+    while data.get(*position).is_some_and(|b| !b.is_ascii_whitespace()) {
+        *position += 1;
+    }
+    Ok(std::str::from_utf8(&data[start..*position])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flat_hdr() {
+        let mut data: Vec<u8> = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        data.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+
+        let image = parse_hdr(&mut &data[..]).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert!((image.texels[0].x - 1.0).abs() < 0.01);
+        assert!((image.texels[0].y - 0.5).abs() < 0.01);
+        assert_eq!(image.texels[1], Color::default());
+    }
+
+    #[test]
+    fn test_parse_netpbm() {
+        let plain = b"P2\n# a comment\n2 1\n255\n0 255\n";
+        let image = parse_netpbm(plain).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.texels, vec![Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)]);
+
+        let mut raw: Vec<u8> = b"P5 1 2 65535\n".to_vec();
+        raw.extend_from_slice(&[0x80, 0x00, 0xff, 0xff]);
+        let image = parse_netpbm(&raw).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        assert!((image.texels[0].x - 0.5).abs() < 0.001);
+        assert_eq!(image.texels[1].x, 1.0);
+    }
+}
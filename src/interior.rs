@@ -0,0 +1,94 @@
+use crate::material::Material;
+
+
+/// The materials a path is currently inside, for nested dielectrics such as
+/// water in a glass (Schmidt and Budge, "Simple Nested Dielectrics in Ray
+/// Traced Images", 2002).
+///
+/// Overlapping objects are modelled by letting the highest priority
+/// material fill the space they share. Surfaces of lower priority objects
+/// inside it are false hits: rays pass straight through them, and the
+/// refractive indices on either side of real surfaces come from the stack.
+pub struct InteriorStack {
+    materials: Vec<Material>,
+}
+
+impl InteriorStack {
+    /// Paths start out in air.
+    pub fn new() -> Self {
+        InteriorStack { materials: vec![] }
+    }
+
+    /// The material filling the space the path is in, or `None` for air.
+    /// Ties go to the most recently entered.
+    pub fn current(self: &Self) -> Option<Material> {
+        self.materials.iter().rev().cloned().max_by_key(|m| m.priority)
+    }
+
+    /// Whether a surface of `material` is hidden inside something with a
+    /// higher priority.
+    pub fn is_false_hit(self: &Self, material: &Material) -> bool {
+        self.current().is_some_and(|current| current.priority > material.priority)
+    }
+
+    /// Refractive index across a surface of `material` from its interior,
+    /// given which side the path is arriving from, at `wavelength` in nm.
+    pub fn outside_ior(self: &Self, material: &Material, front_face: bool, wavelength: Option<f64>) -> f64 {
+        let current = match front_face {
+            true => self.current(),
+            false => {
+                let mut remaining = InteriorStack { materials: self.materials.clone() };
+                remaining.exit(material);
+                remaining.current()
+            }
+        };
+
+        current.and_then(|m| m.ior(wavelength)).unwrap_or(1.0)
+    }
+
+    /// Update the stack for a path passing through a surface of `material`.
+    pub fn cross(self: &mut Self, material: &Material, front_face: bool) {
+        match front_face {
+            true => self.materials.push(material.clone()),
+            false => self.exit(material),
+        }
+    }
+
+    /// Paths can leave materials they were never seen entering, such as
+    /// when the camera is inside one. That leaves the stack as it is.
+    fn exit(self: &mut Self, material: &Material) {
+        if let Some(i) = self.materials.iter().rposition(|m| m == material) {
+            self.materials.remove(i);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{Ior, MaterialType};
+    use crate::vector::Color;
+
+    #[test]
+    fn test_water_in_glass() {
+        let glass = Material::new(MaterialType::Dielectric(Ior::Constant(1.5), Color::default())).with_priority(2);
+        let water = Material::new(MaterialType::Dielectric(Ior::Constant(1.33), Color::default())).with_priority(1);
+        let mut stack = InteriorStack::new();
+
+        // Through the glass wall, into the water, which overlaps the glass a little.
+        assert_eq!(stack.outside_ior(&glass, true, None), 1.0);
+        stack.cross(&glass, true);
+        assert!(stack.is_false_hit(&water));
+        stack.cross(&water, true);
+        assert_eq!(stack.outside_ior(&glass, false, None), 1.33);
+        stack.cross(&glass, false);
+        assert!(stack.current().as_ref() == Some(&water));
+
+        // Out through the water's surface.
+        assert!(!stack.is_false_hit(&water));
+        assert_eq!(stack.outside_ior(&water, false, None), 1.0);
+        stack.cross(&water, false);
+        assert!(stack.current().is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use crate::utility::degrees_to_radians;
+use crate::vector::{Color, Point3, Vec3};
+
+
+/// Lights with no area, which can only be reached through light sampling.
+#[derive(Copy, Clone)]
+pub enum Light {
+    /// Point(position, intensity)
+    Point(Point3, Color),
+
+    /// Spot(position, direction, intensity, cos_inner, cos_outer)
+    /// Use `Light::spot` rather than building this by hand.
+    Spot(Point3, Vec3, Color, f64, f64),
+
+    /// Directional(direction the light travels in, irradiance)
+    Directional(Vec3, Color),
+}
+
+impl Light {
+    /// A spot light at `position` aimed at `target`. Full intensity within
+    /// `inner_angle` of the axis, falling off to nothing at `outer_angle`.
+    /// Angles are in degrees, measured from the axis.
+    pub fn spot(position: Point3, target: Point3, intensity: Color, inner_angle: f64, outer_angle: f64) -> Self {
+        let cos_outer = degrees_to_radians(outer_angle).cos();
+        let cos_inner = degrees_to_radians(inner_angle.min(outer_angle)).cos();
+
+        Light::Spot(position, (target - position).unit_vector(), intensity, cos_inner, cos_outer)
+    }
+
+    /// A sun-like light shining along `direction`.
+    pub fn directional(direction: Vec3, irradiance: Color) -> Self {
+        Light::Directional(direction.unit_vector(), irradiance)
+    }
+
+    /// Returns `(direction towards the light, radiance, distance to the light)`
+    /// as seen from `p`. The distance is infinite for directional lights.
+    pub fn sample(self: &Self, p: &Point3) -> (Vec3, Color, f64) {
+        match *self {
+            Light::Point(position, intensity) => {
+                let (direction, distance) = Self::towards(p, &position);
+                (direction, intensity / (distance * distance), distance)
+            }
+            Light::Spot(position, axis, intensity, cos_inner, cos_outer) => {
+                let (direction, distance) = Self::towards(p, &position);
+                let falloff = Self::spot_falloff((-direction).dot(&axis), cos_inner, cos_outer);
+                (direction, falloff * intensity / (distance * distance), distance)
+            }
+            Light::Directional(direction, irradiance) => (-direction, irradiance, f64::INFINITY),
+        }
+    }
+
+    fn towards(p: &Point3, position: &Point3) -> (Vec3, f64) {
+        let to_light = *position - *p;
+        let distance = to_light.length();
+        (to_light / distance, distance)
+    }
+
+    /// Smoothstep between the outer and inner cones.
+    fn spot_falloff(cos_theta: f64, cos_inner: f64, cos_outer: f64) -> f64 {
+        if cos_theta <= cos_outer {
+            return 0.0;
+        }
+        if cos_theta >= cos_inner {
+            return 1.0;
+        }
+
+        let t = (cos_theta - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
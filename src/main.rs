@@ -7,65 +7,53 @@ mod sphere;
 mod utility;
 mod camera;
 mod material;
-
+mod environment;
+mod image_reader;
+mod distribution;
+mod onb;
+mod sky;
+mod light;
+mod scene;
+mod aabb;
+mod quad;
+mod medium;
+mod bvh;
+mod plane;
+mod world;
+mod solver;
+mod quadric;
+mod csg;
+mod sdf;
+mod heightfield;
+mod interior;
+mod spectrum;
+mod microfacet;
+mod principled;
+mod texture;
+mod thin_film;
+mod subsurface;
+mod voxel;
+mod phase;
+mod bump;
+mod sampler;
+
+use std::env;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Instant;
 
-use crate::camera::Camera;
-use crate::hittable::Hittables;
-use crate::material::Material;
+use crate::environment::{Environment, EnvironmentMap};
+use crate::world::World;
 use crate::image_writer::{Image, Pixels};
-use crate::color::{make_ray_color, make_color};
-use crate::material::MaterialType;
-use crate::sphere::Sphere;
+use crate::color::{make_ray_color, make_ray_color_spectral, make_color};
+use crate::scene::Scene;
 use crate::vector::{Point3, Vec3, Color};
 use crate::ray::Ray;
+use crate::sky::PhysicalSky;
+use crate::sampler::{Sampler, SamplerKind};
 use crate::utility::random_f64;
 
 
-fn random_world() -> Hittables<Sphere>{
-    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
-
-    let mut world: Hittables<Sphere> = Hittables::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_material));
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let choose_mat = random_f64(None, None);
-            let center: Point3 = Point3::new(a as f64 + 0.9 * random_f64(None, None), 0.2, b as f64 + 0.9 * random_f64(None, None));
-
-            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                let sphere_material: Material;
-
-                if choose_mat < 0.5 {
-                    let albedo  = Color::random() * Color::random();
-                    sphere_material = Material::new(MaterialType::Lambertian(albedo));
-                    world.add(Sphere::new(center, 0.2, sphere_material));
-                } else if choose_mat < 0.8 {
-                    let albedo = Color::random();
-                    let fuzz = random_f64(Some(0.0), Some(0.5));
-                    sphere_material = Material::new(MaterialType::Metal(albedo, fuzz));
-                    world.add(Sphere::new(center, 0.2, sphere_material));
-                } else {
-                    sphere_material = Material::new(MaterialType::Dielectric(1.5));
-                    world.add(Sphere::new(center, 0.2, sphere_material));
-                }
-            }
-        }
-    }
-
-    let material1 = Material::new(MaterialType::Dielectric(1.5));
-    world.add(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, material1));
-
-    let material2 = Material::new(MaterialType::Lambertian(Color::new(0.4, 0.2, 0.1)));
-    world.add(Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, material2));
-
-    let material3 = Material::new(MaterialType::Metal(Color::new(0.7, 0.6, 0.5), 0.0));
-    world.add(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, material3));
-
-    world
-}
-
 fn main() {
     let file_path: Option<&str> = Some("render.ppm");
 
@@ -75,18 +63,68 @@ fn main() {
     let samples_per_pixel = 500;
     let max_depth = 50;
 
-    
-    // Camera
-    let lookfrom = Point3::new(13.0, 2.0, 3.0);
-    let lookat = Point3::new(0.0, 0.0, 0.0);
-    let vup = Vec3::new(0.0, 1.0, 0.0);
-    let dist_to_focus = 10.0;
-    let aperture = 0.1;
 
-    let cam = Arc::new(Camera::new(lookfrom, lookat, vup, 20.0, aspect_ratio, aperture, dist_to_focus));
+    // Usage: `spectra [--scene random|lights|quadrics|csg|sdf|terrain|nested|dispersion|metals|frosted|principled|coated|diffuse|iridescence|subsurface|bumps|cutout|clouds|cornell|smoke]
+    //                 [--heightmap file.pgm]
+    //                 [--mtl file.mtl]
+    //                 [--voxels file.raw|.txt nx ny nz]
+    //                 [--env file.hdr|.exr [rotation] [intensity]]
+    //                 [--sky [elevation] [azimuth] [turbidity]]
+    //                 [--spectral]
+    //                 [--sampler independent|stratified|sobol|halton|bluenoise]`
+    let args: Vec<String> = env::args().skip(1).collect();
+    let spectral = args.iter().any(|a| a == "--spectral");
+    let sampler_kind = match flag_value(&args, "--sampler") {
+        Some(name) => SamplerKind::from_name(&name).unwrap_or_else(|| panic!("Unknown sampler: {}", name)),
+        None => SamplerKind::Independent,
+    };
 
     // World
-    let world: Arc<Hittables<Sphere>> = Arc::new(random_world());
+    let mut scene: Scene<World> = match flag_value(&args, "--scene").as_deref() {
+        None | Some("random") => scene::random_spheres(aspect_ratio),
+        Some("lights") => scene::punctual_lights(aspect_ratio),
+        Some("quadrics") => scene::quadrics(aspect_ratio),
+        Some("csg") => scene::csg(aspect_ratio),
+        Some("sdf") => scene::sdf_shapes(aspect_ratio),
+        Some("terrain") => scene::terrain(aspect_ratio, flag_value(&args, "--heightmap").as_deref()),
+        Some("nested") => scene::nested_dielectrics(aspect_ratio),
+        Some("dispersion") => scene::dispersion(aspect_ratio),
+        Some("metals") => scene::metals(aspect_ratio),
+        Some("frosted") => scene::frosted_glass(aspect_ratio),
+        Some("principled") => scene::principled(aspect_ratio, flag_value(&args, "--mtl").as_deref()),
+        Some("coated") => scene::coated(aspect_ratio),
+        Some("diffuse") => scene::diffuse(aspect_ratio),
+        Some("iridescence") => scene::iridescence(aspect_ratio),
+        Some("subsurface") => scene::subsurface(aspect_ratio),
+        Some("bumps") => scene::bumps(aspect_ratio),
+        Some("cutout") => scene::cutout(aspect_ratio),
+        Some("clouds") => {
+            let size = flag_numbers(&args, "--voxels");
+            let voxels = flag_value(&args, "--voxels").filter(|_| size.len() == 3);
+            scene::clouds(aspect_ratio, voxels.as_deref().map(|path| (path, [size[0] as usize, size[1] as usize, size[2] as usize])))
+        }
+        Some("cornell") => scene::cornell_box(aspect_ratio),
+        Some("smoke") => scene::cornell_smoke(aspect_ratio),
+        Some(other) => panic!("Unknown scene: {}", other),
+    };
+
+    // Background, if not the scene's own
+    if let Some(path) = flag_value(&args, "--env") {
+        let numbers = flag_numbers(&args, "--env");
+        let rotation = numbers.first().copied().unwrap_or(0.0);
+        let intensity = numbers.get(1).copied().unwrap_or(1.0);
+        scene.environment = Environment::Map(EnvironmentMap::load(&path, rotation, intensity).expect("Could not load environment map"));
+    }
+    if args.iter().any(|a| a == "--sky") {
+        let numbers = flag_numbers(&args, "--sky");
+        let elevation = numbers.first().copied().unwrap_or(30.0);
+        let azimuth = numbers.get(1).copied().unwrap_or(0.0);
+        let turbidity = numbers.get(2).copied().unwrap_or(3.0);
+        scene.environment = Environment::Sky(PhysicalSky::new(elevation, azimuth, turbidity, 1.0));
+    }
+
+    scene.world.build();
+    let scene = Arc::new(scene);
 
     // Pixel data
     let pixels: Arc<Mutex<Pixels>> = Arc::new(Mutex::new(vec![vec![0_i64; width as usize]; height as usize]));
@@ -98,21 +136,26 @@ fn main() {
 
     for i in 0..worker_count {
         let inner_pixels = pixels.clone();
-        let inner_world = world.clone();
-        let inner_cam = cam.clone();
+        let inner_scene = scene.clone();
 
         // And.... render....!
         results.push(thread::spawn(move || {
+            let mut sampler = Sampler::new(sampler_kind, samples_per_pixel);
             for y in 0..height {
                 // println!("Scan lines remaining: {}", height-y);
                 for x in (i * width/worker_count)..((i+1) * width/worker_count) {
                     let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                    for _ in 0..samples_per_pixel {
-                        let u: f64 = (x as f64 + random_f64(None, None)) / (width - 1) as f64;
-                        let v: f64 = ((height - y) as f64 + random_f64(None, None)) / (height - 1) as f64;
-                        let ray: Ray = inner_cam.get_ray(u, v);
-
-                        pixel_color += make_ray_color(ray, &*inner_world, max_depth);
+                    for s in 0..samples_per_pixel {
+                        sampler.start_pixel_sample(x, y, s);
+                        let (dx, dy) = sampler.get_2d();
+                        let u: f64 = (x as f64 + dx) / (width - 1) as f64;
+                        let v: f64 = ((height - y) as f64 + dy) / (height - 1) as f64;
+                        let ray: Ray = inner_scene.camera.get_ray(u, v, &mut sampler);
+
+                        pixel_color += match spectral {
+                            true => make_ray_color_spectral(ray, &*inner_scene, max_depth, &mut sampler),
+                            false => make_ray_color(ray, &*inner_scene, max_depth, &mut sampler),
+                        };
                     }
 
                     let mut inner_pixels = inner_pixels.lock().unwrap();
@@ -145,3 +188,20 @@ fn main() {
     let hours = (duration.as_secs() / 60) / 60;
     println!("Wrote to image in {}:{}:{}", hours, minutes, seconds);
 }
+
+/// The argument right after `flag`, if it isn't another flag.
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    let i = args.iter().position(|a| a == flag)?;
+    args.get(i + 1).filter(|v| !v.starts_with("--")).cloned()
+}
+
+/// The numbers following `flag` (and its value, if it takes one).
+fn flag_numbers(args: &[String], flag: &str) -> Vec<f64> {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => args[i + 1..].iter()
+            .skip_while(|a| a.parse::<f64>().is_err() && !a.starts_with("--"))
+            .map_while(|a| a.parse::<f64>().ok())
+            .collect(),
+        None => vec![],
+    }
+}
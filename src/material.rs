@@ -1,21 +1,103 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::bump::Bump;
 use crate::hittable::HitRecord;
+use crate::microfacet::{eval_dielectric, fresnel_conductor, fresnel_dielectric, oren_nayar, rgb_ior, sample_dielectric, sheen, TrowbridgeReitz};
+use crate::onb::Onb;
+use crate::phase::PhaseFunction;
+use crate::principled::Principled;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::spectrum::Channels;
+use crate::texture::Texture;
+use crate::thin_film::ThinFilm;
 use crate::Color;
-use crate::utility::random_f64;
 use crate::vector::Vec3;
 
-#[derive(Copy, Clone)]
+#[derive(Clone, PartialEq)]
 pub enum MaterialType {
     Lambertian(Color),
 
+    /// OrenNayar(albedo, sigma). Rough diffuse, like clay or moon dust, with
+    /// `sigma` the spread of the facets' slopes in radians. See
+    /// `microfacet::oren_nayar`.
+    OrenNayar(Color, f64),
+
+    /// Sheen(albedo, sheen, roughness). Diffuse with a soft highlight at
+    /// grazing angles, for cloth. See `microfacet::sheen`.
+    Sheen(Color, Color, f64),
+
     /// Metal(color, fuzziness)
     Metal(Color, f64),
 
-    // Dielectric(refraction_index)
-    Dielectric(f64)
+    /// Dielectric(refraction_index, absorption). Light travelling inside is
+    /// attenuated by `exp(-absorption * distance)`; see `absorption_for`.
+    Dielectric(Ior, Color),
+
+    /// RoughDielectric(refraction_index, absorption, roughness). Frosted
+    /// glass, using the GGX microfacet distribution.
+    RoughDielectric(Ior, Color, f64),
+
+    /// DiffuseLight(emitted radiance). Emits from its front face and
+    /// doesn't scatter.
+    DiffuseLight(Color),
+
+    /// Volume(albedo, phase_function). Scatters in directions following the
+    /// phase function, for participating media.
+    Volume(Color, PhaseFunction),
+
+    /// Conductor(eta, k, roughness). A rough metal with complex refractive
+    /// index `eta + i k`, using the GGX microfacet distribution. See
+    /// `MaterialType::conductor` for measured metals.
+    Conductor(Color, Color, f64),
+
+    /// Subsurface(albedo, mean_free_path, phase_function). Translucent, like
+    /// skin, wax or milk: light scatters about inside, with the average
+    /// distance between scattering events and the albedo of each given per
+    /// channel, before coming back out. The integrator walks these; see
+    /// `subsurface`.
+    Subsurface(Color, Color, PhaseFunction),
+
+    /// Principled(parameters). One material for most things, in the style
+    /// of Disney's; see `Principled`.
+    Principled(Principled),
+
+    /// Layered(coat, base). A clear dielectric coat over another material,
+    /// like car paint or varnish. See `MaterialType::layered`.
+    Layered(Coat, Arc<Material>),
+
+    /// Mix(a, b, weight). Each hit picks `b` with probability the weight's
+    /// luminance there, and `a` otherwise. See `MaterialType::mix`.
+    Mix(Arc<Material>, Arc<Material>, Texture),
 }
 
 impl MaterialType {
+    /// The absorption coefficient that leaves `transmittance` of the light
+    /// after travelling `distance` through a medium. Handy for tinted glass,
+    /// where the color at a given thickness is easier to pick.
+    pub fn absorption_for(transmittance: Color, distance: f64) -> Color {
+        let channel = |t: f64| -t.max(1e-6).ln() / distance;
+        Color::new(channel(transmittance.x), channel(transmittance.y), channel(transmittance.z))
+    }
+
+    /// A conductor from one of the measured tables in `microfacet`, such as
+    /// `microfacet::GOLD`.
+    pub fn conductor(table: &[(f64, f64, f64)], roughness: f64) -> Self {
+        let (eta, k) = rgb_ior(table);
+        MaterialType::Conductor(eta, k, roughness)
+    }
+
+    /// `coat` over `base`.
+    pub fn layered(coat: Coat, base: Material) -> Self {
+        MaterialType::Layered(coat, Arc::new(base))
+    }
+
+    /// A blend of `a` and `b`.
+    pub fn mix(a: Material, b: Material, weight: Texture) -> Self {
+        MaterialType::Mix(Arc::new(a), Arc::new(b), weight)
+    }
+
     fn new(self: Self) -> Self {
         if let MaterialType::Metal(c, f) = self {
             let fuzz = match f < 1.0 {
@@ -31,26 +113,411 @@ impl MaterialType {
 }
 
 
-#[derive(Copy, Clone)]
+/// Refractive index, possibly varying with wavelength. Wavelengths are
+/// in micrometres in the formulae below.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Ior {
+    Constant(f64),
+
+    /// Cauchy(a, b): `n = a + b / λ²`
+    Cauchy(f64, f64),
+
+    /// Sellmeier(b, c): `n² = 1 + Σ b_i λ² / (λ² - c_i)`
+    Sellmeier([f64; 3], [f64; 3]),
+}
+
+impl Ior {
+    /// Schott SF11 dense flint glass, which disperses strongly.
+    pub const SF11: Ior = Ior::Sellmeier([1.73759695, 0.313747346, 1.89878101], [0.013188707, 0.0623068142, 155.23629]);
+
+    /// Diamond, after Peter (1923).
+    pub const DIAMOND: Ior = Ior::Sellmeier([0.3306, 4.3356, 0.0], [0.030625, 0.011236, 0.0]);
+
+    /// The index at `wavelength` in nm, or at the sodium d-line (587.6 nm),
+    /// where indices are usually quoted, if there's none.
+    pub fn at(self: Self, wavelength: Option<f64>) -> f64 {
+        let lambda = wavelength.unwrap_or(587.6) / 1000.0;
+        let lambda2 = lambda * lambda;
+
+        match self {
+            Ior::Constant(n) => n,
+            Ior::Cauchy(a, b) => a + b / lambda2,
+            Ior::Sellmeier(b, c) => {
+                let sum: f64 = (0..3).map(|i| b[i] * lambda2 / (lambda2 - c[i])).sum();
+                (1.0 + sum).sqrt()
+            }
+        }
+    }
+
+    pub fn is_dispersive(self: Self) -> bool {
+        !matches!(self, Ior::Constant(_))
+    }
+}
+
+
+/// A thin, clear dielectric layer, for `MaterialType::Layered`. Light
+/// reaching the base is taken to leave along the directions it would have
+/// without the coat, but loses what the coat reflects and absorbs.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Coat {
+    pub ior: f64,
+
+    /// GGX roughness of the coat's surface.
+    pub roughness: f64,
+
+    /// Absorption coefficient, as for `Dielectric`, for tinted coats.
+    pub absorption: Color,
+    pub thickness: f64,
+}
+
+impl Coat {
+    pub fn new(ior: f64, roughness: f64, absorption: Color, thickness: f64) -> Self {
+        Coat { ior, roughness, absorption, thickness }
+    }
+
+    /// How much light gets into the coat at `cos_o` to the normal, down to
+    /// the base and back out at `cos_i`.
+    fn transmittance(self: &Self, cos_o: f64, cos_i: f64) -> Color {
+        // Cosines inside the coat, after refraction.
+        let inside = |cos: f64| (1.0 - (1.0 - cos * cos) / (self.ior * self.ior)).max(1e-6).sqrt();
+        let distance = self.thickness * (1.0 / inside(cos_o) + 1.0 / inside(cos_i));
+
+        let fresnel = (1.0 - fresnel_dielectric(cos_o, self.ior)) * (1.0 - fresnel_dielectric(cos_i, self.ior));
+        fresnel * Color::new(
+            (-self.absorption.x * distance).exp(),
+            (-self.absorption.y * distance).exp(),
+            (-self.absorption.z * distance).exp(),
+        )
+    }
+
+    /// Probability of sampling the coat rather than the base, for light
+    /// leaving at `cos_o` to the normal. Follows the Fresnel reflectance,
+    /// but leaves a fair share for each.
+    fn probability(self: &Self, cos_o: f64) -> f64 {
+        fresnel_dielectric(cos_o, self.ior).clamp(0.25, 0.75)
+    }
+}
+
+
+/// Where a path reaching a surface has come from, for `Material::scatter`.
+pub struct ScatterContext<'a> {
+    /// Refractive index of whatever is on the other side of the surface
+    /// from the material's interior: 1 for air.
+    pub outside_ior: f64,
+
+    /// What the path's colors stand for. Attenuations, like `eval`'s
+    /// values, come back in these channels.
+    pub channels: &'a Channels,
+
+    /// Where random choices come from.
+    pub sampler: &'a mut Sampler,
+}
+
+
+#[derive(Clone, PartialEq)]
 pub struct Material {
-    mat_type: MaterialType
+    mat_type: MaterialType,
+
+    /// Where dielectrics overlap, the one with the highest priority fills
+    /// the shared space. See `InteriorStack`.
+    pub priority: u32,
+
+    /// An iridescent coating. Only `Dielectric` and `Conductor` can have
+    /// one; see `with_film`.
+    pub film: Option<ThinFilm>,
+
+    /// Tilts the shading normal before scattering. See `Bump`.
+    pub bump: Option<Bump>,
+
+    /// Opacity, from the texture's luminance, for cutouts like leaves and
+    /// fences. Rays pass through transparent parts as if nothing was there.
+    pub alpha: Option<Texture>,
 }
 
 impl Material {
     pub fn new(mat_type: MaterialType) -> Self {
-        Material { mat_type }
+        Material { mat_type, priority: 0, film: None, bump: None, alpha: None }
+    }
+
+    pub fn with_priority(self: Self, priority: u32) -> Self {
+        Material { priority, ..self }
+    }
+
+    pub fn with_film(self: Self, film: ThinFilm) -> Self {
+        assert!(
+            matches!(self.mat_type, MaterialType::Dielectric(_, _) | MaterialType::Conductor(_, _, _)),
+            "Thin films are only supported on dielectrics and conductors",
+        );
+        Material { film: Some(film), ..self }
+    }
+
+    pub fn with_normal_map(self: Self, texture: Texture) -> Self {
+        Material { bump: Some(Bump::NormalMap(texture)), ..self }
+    }
+
+    /// Heights from `texture`, up to `scale` in world units.
+    pub fn with_bump_map(self: Self, texture: Texture, scale: f64) -> Self {
+        Material { bump: Some(Bump::Height(texture, scale)), ..self }
+    }
+
+    pub fn with_alpha(self: Self, texture: Texture) -> Self {
+        Material { alpha: Some(texture), ..self }
+    }
+
+    /// How much of the surface is there at `rec`, from 0 for none of it to
+    /// 1 for all. A `Mix` is there as much as its materials are, on average.
+    pub fn opacity(self: &Self, rec: &HitRecord) -> f64 {
+        let alpha = match &self.alpha {
+            Some(texture) => texture.value(rec.u, rec.v, &rec.p).luminance().clamp(0.0, 1.0),
+            None => 1.0,
+        };
+
+        match self.mat_type {
+            MaterialType::Mix(ref a, ref b, ref weight) => {
+                let weight = mix_weight(weight, rec);
+                alpha * ((1.0 - weight) * a.opacity(rec) + weight * b.opacity(rec))
+            }
+            _ => alpha,
+        }
+    }
+
+    /// Refractive index of the material's interior at `wavelength`, for
+    /// materials that rays can pass into.
+    pub fn ior(self: &Self, wavelength: Option<f64>) -> Option<f64> {
+        match self.mat_type {
+            MaterialType::Dielectric(ir, _) | MaterialType::RoughDielectric(ir, _, _) => Some(ir.at(wavelength)),
+            MaterialType::Principled(p) if p.transmission > 0.0 => Some(p.ior),
+            // Under its coat.
+            MaterialType::Layered(_, ref base) => base.ior(wavelength),
+            _ => None,
+        }
+    }
+
+    /// Settle any `Mix` at `rec` into one of its materials, at random.
+    /// Having been hit, it's each as often as that one is there, so a
+    /// material that's cut out where the other isn't never gets picked.
+    pub fn resolve(self: &Self, rec: &HitRecord, sampler: &mut Sampler) -> Material {
+        match self.mat_type {
+            MaterialType::Mix(ref a, ref b, ref weight) => {
+                let weight = mix_weight(weight, rec);
+                let (a_share, b_share) = ((1.0 - weight) * a.opacity(rec), weight * b.opacity(rec));
+                let pick_b = match a_share + b_share > 0.0 {
+                    true => sampler.get_1d() * (a_share + b_share) < b_share,
+                    false => sampler.get_1d() < weight,
+                };
+                match pick_b {
+                    true => b.resolve(rec, sampler),
+                    false => a.resolve(rec, sampler),
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Albedo, mean free path and phase function, for subsurface materials.
+    pub fn subsurface(self: &Self) -> Option<(Color, Color, PhaseFunction)> {
+        match self.mat_type {
+            MaterialType::Subsurface(albedo, mean_free_path, phase) => Some((albedo, mean_free_path, phase)),
+            _ => None,
+        }
+    }
+
+    pub fn has_interior(self: &Self) -> bool {
+        self.ior(None).is_some()
+    }
+
+    /// Whether light of different wavelengths scatters in different
+    /// directions, so paths can't carry more than one.
+    pub fn is_dispersive(self: &Self) -> bool {
+        match self.mat_type {
+            MaterialType::Dielectric(ir, _) | MaterialType::RoughDielectric(ir, _, _) => ir.is_dispersive(),
+            _ => false,
+        }
     }
 
-    pub fn scatter(self: Self, r_in: &Ray, rec: &HitRecord, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+    /// Fraction of light left after travelling `distance` through the
+    /// material's interior.
+    pub fn transmittance(self: &Self, distance: f64) -> Color {
         match self.mat_type {
-            MaterialType::Lambertian(c) => Material::scatter_lambertian(c, r_in, rec, attentuation, scattered),
-            MaterialType::Metal(c, f) => Material::scatter_metal(c, f, r_in, rec, attentuation, scattered),
-            MaterialType::Dielectric(ir) => Material::scatter_dielectric(ir, r_in, rec, attentuation, scattered),
+            MaterialType::Dielectric(_, a) | MaterialType::RoughDielectric(_, a, _) => Color::new(
+                (-a.x * distance).exp(),
+                (-a.y * distance).exp(),
+                (-a.z * distance).exp(),
+            ),
+            MaterialType::Layered(_, ref base) => base.transmittance(distance),
+            _ => Color::new(1.0, 1.0, 1.0),
         }
     }
 
-    fn scatter_lambertian(c: Color, r_in: &Ray, rec: &HitRecord, attentuation: &mut Color, scattered: &mut Ray) -> bool {
-        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
+    pub fn scatter(self: &Self, r_in: &Ray, rec: &HitRecord, context: &mut ScatterContext, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+        let channels = context.channels;
+        let wavelength = channels.wavelength();
+
+        match self.mat_type {
+            MaterialType::Lambertian(c) => Material::scatter_lambertian(channels.lift(c), r_in, rec, context.sampler, attentuation, scattered),
+            MaterialType::OrenNayar(_, _) | MaterialType::Sheen(_, _, _) => self.scatter_cosine(r_in, rec, context, attentuation, scattered),
+            MaterialType::Metal(c, f) => Material::scatter_metal(channels.lift(c), f, r_in, rec, context.sampler, attentuation, scattered),
+            MaterialType::Dielectric(ir, _) => Material::scatter_dielectric(ir.at(wavelength), self.film, r_in, rec, context, attentuation, scattered),
+            MaterialType::RoughDielectric(ir, _, roughness) => {
+                let distribution = TrowbridgeReitz::new(roughness);
+                match distribution.is_smooth() {
+                    true => Material::scatter_dielectric(ir.at(wavelength), None, r_in, rec, context, attentuation, scattered),
+                    false => Material::scatter_rough_dielectric(&distribution, relative_ior(ir.at(wavelength), context.outside_ior, rec), r_in, rec, context.sampler, attentuation, scattered),
+                }
+            }
+            MaterialType::DiffuseLight(_) => false,
+            // Already walked by the integrator, from outside.
+            MaterialType::Subsurface(_, _, _) => false,
+            MaterialType::Volume(c, phase) => Material::scatter_volume(channels.lift(c), &phase, r_in, rec, context.sampler, attentuation, scattered),
+            MaterialType::Conductor(_, _, _) => self.scatter_conductor(r_in, rec, context, attentuation, scattered),
+            MaterialType::Principled(p) => Material::scatter_principled(&p, relative_ior(p.ior, context.outside_ior, rec), r_in, rec, context, attentuation, scattered),
+            MaterialType::Layered(_, _) => self.scatter_layered(r_in, rec, context, attentuation, scattered),
+            MaterialType::Mix(_, _, _) => self.resolve(rec, context.sampler).scatter(r_in, rec, context, attentuation, scattered),
+        }
+    }
+
+    /// Radiance emitted towards the ray that produced `rec`.
+    pub fn emitted(self: &Self, rec: &HitRecord) -> Color {
+        match self.mat_type {
+            MaterialType::DiffuseLight(c) if rec.front_face => c,
+            MaterialType::Layered(_, ref base) => base.emitted(rec),
+            MaterialType::Mix(ref a, ref b, ref weight) => {
+                let w = mix_weight(weight, rec);
+                (1.0 - w) * a.emitted(rec) + w * b.emitted(rec)
+            }
+            _ => Color::default(),
+        }
+    }
+
+    /// Specular materials scatter into a single direction (or a few), so
+    /// they can't make use of light sampling.
+    pub fn is_specular(self: &Self) -> bool {
+        match self.mat_type {
+            MaterialType::Lambertian(_) => false,
+            MaterialType::OrenNayar(_, _) => false,
+            MaterialType::Sheen(_, _, _) => false,
+            MaterialType::Metal(_, _) => true,
+            MaterialType::Dielectric(_, _) => true,
+            MaterialType::RoughDielectric(_, _, roughness) => TrowbridgeReitz::new(roughness).is_smooth(),
+            MaterialType::DiffuseLight(_) => false,
+            MaterialType::Subsurface(_, _, _) => true,
+            MaterialType::Volume(_, _) => false,
+            MaterialType::Conductor(_, _, roughness) => TrowbridgeReitz::new(roughness).is_smooth(),
+            MaterialType::Principled(_) => false,
+            MaterialType::Layered(_, ref base) => base.is_specular(),
+            MaterialType::Mix(ref a, ref b, _) => a.is_specular() || b.is_specular(),
+        }
+    }
+
+    /// BSDF times cosine for light arriving from `direction`, in
+    /// `channels`, with `outside_ior` and `channels` as in `ScatterContext`.
+    /// Only meaningful for non-specular materials.
+    pub fn eval(self: &Self, r_in: &Ray, rec: &HitRecord, outside_ior: f64, channels: &Channels, direction: &Vec3) -> Color {
+        if !rec.sides_agree(direction) {
+            return Color::default();
+        }
+        let wavelength = channels.wavelength();
+
+        match self.mat_type {
+            MaterialType::Lambertian(c) => {
+                let cosine = rec.normal.dot(&direction.unit_vector()).max(0.0);
+                channels.lift(c) * (cosine / PI)
+            }
+            MaterialType::OrenNayar(c, sigma) => match microfacet_frame(r_in, rec, direction) {
+                Some((wo, wi, _)) => channels.lift(c) * (oren_nayar(sigma, &wo, &wi) * wi.z / PI),
+                None => Color::default(),
+            },
+            MaterialType::Sheen(c, sheen_color, roughness) => match microfacet_frame(r_in, rec, direction) {
+                Some((wo, wi, _)) => (channels.lift(c) / PI + channels.lift(sheen_color) * sheen(roughness, &wo, &wi)) * wi.z,
+                None => Color::default(),
+            },
+            MaterialType::Volume(c, phase) => channels.lift(c) * phase.eval(r_in.direction.unit_vector().dot(&direction.unit_vector())),
+            MaterialType::Conductor(eta, k, roughness) => {
+                let (wo, wi, wm) = match microfacet_frame(r_in, rec, direction) {
+                    Some(frame) => frame,
+                    None => return Color::default(),
+                };
+                let distribution = TrowbridgeReitz::new(roughness);
+
+                // D F G / (4 cos_o cos_i), times cos_i.
+                self.fresnel_conductor(wo.dot(&wm), eta, k, outside_ior, channels) * (distribution.d(&wm) * distribution.g(&wo, &wi) / (4.0 * wo.z))
+            }
+            MaterialType::RoughDielectric(ir, _, roughness) => {
+                let (wo, wi) = local_directions(r_in, rec, direction);
+                let (f, _) = eval_dielectric(&TrowbridgeReitz::new(roughness), relative_ior(ir.at(wavelength), outside_ior, rec), &wo, &wi);
+                Color::new(f, f, f)
+            }
+            MaterialType::Principled(p) => {
+                let (wo, wi) = local_directions(r_in, rec, direction);
+                channels.lift(p.eval(relative_ior(p.ior, outside_ior, rec), &wo, &wi).0)
+            }
+            MaterialType::Layered(coat, ref base) => {
+                let (wo, wi) = local_directions(r_in, rec, direction);
+                if wo.z <= 0.0 {
+                    return Color::default();
+                }
+                let through_base = base.eval(r_in, rec, coat.ior, channels, direction) * channels.lift(coat.transmittance(wo.z, wi.z.abs()));
+
+                // The coat only reflects, but the base may transmit.
+                match microfacet_frame(r_in, rec, direction) {
+                    Some((wo, wi, wm)) => {
+                        let distribution = TrowbridgeReitz::new(coat.roughness);
+                        let reflected = fresnel_dielectric(wo.dot(&wm), coat.ior) * distribution.d(&wm) * distribution.g(&wo, &wi) / (4.0 * wo.z);
+                        Color::new(reflected, reflected, reflected) + through_base
+                    }
+                    None => through_base,
+                }
+            }
+            MaterialType::Mix(ref a, ref b, ref weight) => {
+                let w = mix_weight(weight, rec);
+                (1.0 - w) * a.eval(r_in, rec, outside_ior, channels, direction) + w * b.eval(r_in, rec, outside_ior, channels, direction)
+            }
+            _ => Color::default(),
+        }
+    }
+
+    /// Solid angle PDF of `scatter` choosing `direction`.
+    /// Only meaningful for non-specular materials.
+    pub fn pdf(self: &Self, r_in: &Ray, rec: &HitRecord, outside_ior: f64, channels: &Channels, direction: &Vec3) -> f64 {
+        match self.mat_type {
+            // `scatter_lambertian` and `scatter_cosine` are cosine weighted.
+            MaterialType::Lambertian(_) | MaterialType::OrenNayar(_, _) | MaterialType::Sheen(_, _, _) => rec.normal.dot(&direction.unit_vector()).max(0.0) / PI,
+            MaterialType::Volume(_, phase) => phase.eval(r_in.direction.unit_vector().dot(&direction.unit_vector())),
+            MaterialType::Conductor(_, _, roughness) => match microfacet_frame(r_in, rec, direction) {
+                // Visible normals, through the Jacobian of reflection.
+                Some((wo, _, wm)) => TrowbridgeReitz::new(roughness).pdf_visible(&wo, &wm) / (4.0 * wo.dot(&wm)),
+                None => 0.0,
+            },
+            MaterialType::RoughDielectric(ir, _, roughness) => {
+                let (wo, wi) = local_directions(r_in, rec, direction);
+                eval_dielectric(&TrowbridgeReitz::new(roughness), relative_ior(ir.at(channels.wavelength()), outside_ior, rec), &wo, &wi).1
+            }
+            MaterialType::Principled(p) => {
+                let (wo, wi) = local_directions(r_in, rec, direction);
+                p.eval(relative_ior(p.ior, outside_ior, rec), &wo, &wi).1
+            }
+            MaterialType::Layered(coat, ref base) => {
+                let (wo, _) = local_directions(r_in, rec, direction);
+                if wo.z <= 0.0 {
+                    return 0.0;
+                }
+                let p_coat = coat.probability(wo.z);
+                let coat_pdf = match microfacet_frame(r_in, rec, direction) {
+                    Some((wo, _, wm)) => TrowbridgeReitz::new(coat.roughness).pdf_visible(&wo, &wm) / (4.0 * wo.dot(&wm)),
+                    None => 0.0,
+                };
+                p_coat * coat_pdf + (1.0 - p_coat) * base.pdf(r_in, rec, coat.ior, channels, direction)
+            }
+            MaterialType::Mix(ref a, ref b, ref weight) => {
+                let w = mix_weight(weight, rec);
+                (1.0 - w) * a.pdf(r_in, rec, outside_ior, channels, direction) + w * b.pdf(r_in, rec, outside_ior, channels, direction)
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn scatter_lambertian(c: Color, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+        let mut scatter_direction = rec.normal + Vec3::sample_unit_vector(sampler.get_2d());
         if scatter_direction.near_zero() {
             scatter_direction = rec.normal;
         }
@@ -59,18 +526,182 @@ impl Material {
         true
     }
 
-    fn scatter_metal(c: Color, fuzz: f64, r_in: &Ray, rec: &HitRecord, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+    /// Cosine weighted sampling, for diffuse-like materials with an `eval`.
+    fn scatter_cosine(self: &Self, r_in: &Ray, rec: &HitRecord, context: &mut ScatterContext, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+        let mut direction = rec.normal + Vec3::sample_unit_vector(context.sampler.get_2d());
+        if direction.near_zero() {
+            direction = rec.normal;
+        }
+
+        let pdf = self.pdf(r_in, rec, context.outside_ior, context.channels, &direction);
+        if pdf <= 0.0 {
+            return false;
+        }
+
+        *scattered = Ray::new(rec.p, direction);
+        *attentuation = self.eval(r_in, rec, context.outside_ior, context.channels, &direction) / pdf;
+        true
+    }
+
+    fn scatter_volume(c: Color, phase: &PhaseFunction, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+        *scattered = Ray::new(rec.p, phase.sample(&r_in.direction.unit_vector(), sampler.get_1d(), sampler.get_2d()));
+        *attentuation = c;
+        true
+    }
+
+    fn scatter_metal(c: Color, fuzz: f64, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler, attentuation: &mut Color, scattered: &mut Ray) -> bool {
         let reflected = Vec3::reflect(r_in.direction.unit_vector(), rec.normal);
-        *scattered = Ray::new(rec.p, reflected + fuzz * Vec3::random_in_unit_sphere());
+        *scattered = Ray::new(rec.p, reflected + fuzz * Vec3::sample_in_unit_sphere(sampler.get_2d(), sampler.get_1d()));
         *attentuation = c;
         scattered.direction.dot(&rec.normal) > 0.0
     }
 
-    fn scatter_dielectric(ir: f64, r_in: &Ray, rec: &HitRecord, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+    /// Fresnel reflectance in `channels` of a conductor under a medium of
+    /// index `outside_ior`, through its film if it has one.
+    fn fresnel_conductor(self: &Self, cos_theta: f64, eta: Color, k: Color, outside_ior: f64, channels: &Channels) -> Color {
+        match self.film {
+            // The film is worked out at each channel's wavelength; the metal
+            // under it is only known in RGB, so is lifted like a color.
+            Some(film) => film.reflectance_conductor(cos_theta, outside_ior, channels.lift(eta), channels.lift(k), channels.wavelengths()),
+            None => channels.lift(fresnel_conductor(cos_theta, eta / outside_ior, k / outside_ior)),
+        }
+    }
+
+    fn scatter_conductor(self: &Self, r_in: &Ray, rec: &HitRecord, context: &mut ScatterContext, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+        let (eta, k, roughness) = match self.mat_type {
+            MaterialType::Conductor(eta, k, roughness) => (eta, k, roughness),
+            _ => return false,
+        };
+
+        let frame = Onb::build_from_w(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.unit_vector());
+        if wo.z <= 0.0 {
+            return false;
+        }
+
+        let distribution = TrowbridgeReitz::new(roughness);
+        if distribution.is_smooth() {
+            *scattered = Ray::new(rec.p, Vec3::reflect(r_in.direction.unit_vector(), rec.normal));
+            *attentuation = self.fresnel_conductor(wo.z, eta, k, context.outside_ior, context.channels);
+            return true;
+        }
+
+        let (u1, u2) = context.sampler.get_2d();
+        let wm = distribution.sample_visible(&wo, u1, u2);
+        let wi = -wo + 2.0 * wo.dot(&wm) * wm;
+        if wi.z <= 0.0 {
+            return false;
+        }
+
+        // With visible normal sampling, most of f cos / pdf cancels out.
+        *scattered = Ray::new(rec.p, frame.local(wi.x, wi.y, wi.z));
+        *attentuation = self.fresnel_conductor(wo.dot(&wm), eta, k, context.outside_ior, context.channels) * (distribution.g(&wo, &wi) / distribution.g1(&wo));
+        true
+    }
+
+    fn scatter_rough_dielectric(distribution: &TrowbridgeReitz, eta: f64, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+        let frame = Onb::build_from_w(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.unit_vector());
+        if wo.z <= 0.0 {
+            return false;
+        }
+
+        let (u0, (u1, u2)) = (sampler.get_1d(), sampler.get_2d());
+        let (wi, weight) = match sample_dielectric(distribution, eta, &wo, u0, u1, u2) {
+            Some(sample) => sample,
+            None => return false,
+        };
+
+        *scattered = Ray::new(rec.p, frame.local(wi.x, wi.y, wi.z));
+        *attentuation = Color::new(weight, weight, weight);
+        true
+    }
+
+    fn scatter_principled(p: &Principled, eta: f64, r_in: &Ray, rec: &HitRecord, context: &mut ScatterContext, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+        let frame = Onb::build_from_w(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.unit_vector());
+
+        let (u0, (u1, u2)) = (context.sampler.get_1d(), context.sampler.get_2d());
+        let wi = match p.sample(eta, &wo, [u0, u1, u2]) {
+            Some(wi) => wi,
+            None => return false,
+        };
+
+        // Several lobes could have chosen `wi`, so weigh it against them all.
+        let (f, pdf) = p.eval(eta, &wo, &wi);
+        if pdf <= 0.0 {
+            return false;
+        }
+
+        *scattered = Ray::new(rec.p, frame.local(wi.x, wi.y, wi.z));
+        *attentuation = context.channels.lift(f) / pdf;
+        true
+    }
+
+    fn scatter_layered(self: &Self, r_in: &Ray, rec: &HitRecord, context: &mut ScatterContext, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+        let (coat, base) = match self.mat_type {
+            MaterialType::Layered(coat, ref base) => (coat, base),
+            _ => return false,
+        };
+
+        let frame = Onb::build_from_w(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.unit_vector());
+        if wo.z <= 0.0 {
+            return false;
+        }
+
+        // Over a specular base the coat may as well be smooth too, and
+        // there's no eval to weigh samples with: pick a layer by Fresnel.
+        if base.is_specular() {
+            let reflectance = fresnel_dielectric(wo.z, coat.ior);
+            if context.sampler.get_1d() < reflectance {
+                *scattered = Ray::new(rec.p, Vec3::reflect(r_in.direction.unit_vector(), rec.normal));
+                *attentuation = Color::new(1.0, 1.0, 1.0);
+                return true;
+            }
+
+            let mut below = ScatterContext { outside_ior: coat.ior, channels: context.channels, sampler: context.sampler };
+            if !base.scatter(r_in, rec, &mut below, attentuation, scattered) {
+                return false;
+            }
+            let cos_i = scattered.direction.unit_vector().dot(&rec.normal).abs();
+            *attentuation = *attentuation * context.channels.lift(coat.transmittance(wo.z, cos_i)) / (1.0 - reflectance);
+            return true;
+        }
+
+        let direction = match context.sampler.get_1d() < coat.probability(wo.z) {
+            true => {
+                let (u1, u2) = context.sampler.get_2d();
+                let wm = TrowbridgeReitz::new(coat.roughness).sample_visible(&wo, u1, u2);
+                let wi = -wo + 2.0 * wo.dot(&wm) * wm;
+                frame.local(wi.x, wi.y, wi.z)
+            }
+            false => {
+                let mut below = ScatterContext { outside_ior: coat.ior, channels: context.channels, sampler: context.sampler };
+                if !base.scatter(r_in, rec, &mut below, attentuation, scattered) {
+                    return false;
+                }
+                scattered.direction
+            }
+        };
+
+        // Either layer could have chosen `direction`, so weigh it against both.
+        let pdf = self.pdf(r_in, rec, context.outside_ior, context.channels, &direction);
+        if pdf <= 0.0 {
+            return false;
+        }
+
+        *scattered = Ray::new(rec.p, direction);
+        *attentuation = self.eval(r_in, rec, context.outside_ior, context.channels, &direction) / pdf;
+        true
+    }
+
+    fn scatter_dielectric(ir: f64, film: Option<ThinFilm>, r_in: &Ray, rec: &HitRecord, context: &mut ScatterContext, attentuation: &mut Color, scattered: &mut Ray) -> bool {
+        let outside_ior = context.outside_ior;
         *attentuation = Color::new(1.0, 1.0, 1.0);
         let refraction_ratio: f64 = match rec.front_face {
-            true => 1.0/ir,
-            false => ir
+            true => outside_ior / ir,
+            false => ir / outside_ior,
         };
 
         let unit_direction = r_in.direction.unit_vector();
@@ -79,9 +710,29 @@ impl Material {
 
         let cannot_reflect = (refraction_ratio * sin_theta) > 1.0;
 
-        let direction: Vec3 = match cannot_reflect || Self::reflectance(cos_theta, refraction_ratio) > random_f64(None, None) {
-            true => Vec3::reflect(unit_direction, rec.normal),
-            false => Vec3::refract(&unit_direction, &rec.normal, refraction_ratio),
+        let direction: Vec3 = match film {
+            // A film reflects each channel differently: reflect in proportion
+            // to the average, and make up the difference in the attenuation.
+            Some(film) if !cannot_reflect => {
+                let (above, below) = match rec.front_face {
+                    true => (outside_ior, ir),
+                    false => (ir, outside_ior),
+                };
+                let reflectance = film.reflectance_dielectric(cos_theta, above, below, context.channels.wavelengths());
+                let p = (reflectance.x + reflectance.y + reflectance.z) / 3.0;
+
+                if context.sampler.get_1d() < p {
+                    *attentuation = reflectance / p;
+                    Vec3::reflect(unit_direction, rec.normal)
+                } else {
+                    *attentuation = (Color::new(1.0, 1.0, 1.0) - reflectance) / (1.0 - p);
+                    Vec3::refract(&unit_direction, &rec.normal, refraction_ratio)
+                }
+            }
+            _ => match cannot_reflect || Self::reflectance(cos_theta, refraction_ratio) > context.sampler.get_1d() {
+                true => Vec3::reflect(unit_direction, rec.normal),
+                false => Vec3::refract(&unit_direction, &rec.normal, refraction_ratio),
+            },
         };
 
         *scattered = Ray::new(rec.p, direction);
@@ -96,3 +747,152 @@ impl Material {
         r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
     }
 }
+
+
+/// Refractive index across the surface over that on the side `rec` was hit from.
+fn relative_ior(ior: f64, outside_ior: f64, rec: &HitRecord) -> f64 {
+    match rec.front_face {
+        true => ior / outside_ior,
+        false => outside_ior / ior,
+    }
+}
+
+/// Probability of a `Mix` picking its second material at `rec`.
+fn mix_weight(weight: &Texture, rec: &HitRecord) -> f64 {
+    weight.value(rec.u, rec.v, &rec.p).luminance().clamp(0.0, 1.0)
+}
+
+/// Outgoing and incoming directions in the local frame of the normal.
+fn local_directions(r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> (Vec3, Vec3) {
+    let frame = Onb::build_from_w(&rec.normal);
+    (frame.to_local(&-r_in.direction.unit_vector()), frame.to_local(&direction.unit_vector()))
+}
+
+/// Outgoing and incoming directions and their half vector, in the local
+/// frame of the normal, if both are above the surface.
+fn microfacet_frame(r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Option<(Vec3, Vec3, Vec3)> {
+    let (wo, wi) = local_directions(r_in, rec, direction);
+    if wo.z <= 0.0 || wi.z <= 0.0 {
+        return None;
+    }
+
+    Some((wo, wi, (wo + wi).unit_vector()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::microfacet;
+    use crate::sampler::SamplerKind;
+
+    /// A hit at the origin on a surface facing up `z`, by a ray arriving
+    /// from `wo`.
+    fn hit(material: Material, wo: Vec3) -> (Ray, HitRecord) {
+        let mut rec = HitRecord::default();
+        rec.normal = Vec3::new(0.0, 0.0, 1.0);
+        rec.front_face = true;
+        rec.material = material;
+        (Ray::new(wo, -wo), rec)
+    }
+
+    /// Average weight of `scatter`'s samples, over all directions.
+    fn albedo(material: &Material, wo: Vec3) -> Color {
+        let (r_in, rec) = hit(material.clone(), wo);
+        let n = 4096;
+        let mut sampler = Sampler::new(SamplerKind::Sobol, n);
+        let mut total = Color::default();
+
+        for i in 0..n {
+            sampler.start_pixel_sample(0, 0, i);
+            let mut context = ScatterContext { outside_ior: 1.0, channels: &Channels::Rgb, sampler: &mut sampler };
+            let (mut attentuation, mut scattered) = (Color::default(), Ray::default());
+            if material.scatter(&r_in, &rec, &mut context, &mut attentuation, &mut scattered) {
+                total += attentuation;
+            }
+        }
+        total / n as f64
+    }
+
+    /// `eval` integrated over all directions, spread evenly.
+    fn integrate_eval(material: &Material, wo: Vec3) -> Color {
+        let (r_in, rec) = hit(material.clone(), wo);
+        let n = 1000;
+        let mut total = Color::default();
+
+        for i in 0..n {
+            for j in 0..n {
+                let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                total += material.eval(&r_in, &rec, 1.0, &Channels::Rgb, &Vec3::sample_unit_vector(u));
+            }
+        }
+        total * (4.0 * PI / (n * n) as f64)
+    }
+
+    #[test]
+    fn test_layered_white_furnace() {
+        // A clear coat over a white base can only lose light, and shouldn't
+        // lose much more than it reflects away from the base.
+        let white = Material::new(MaterialType::Lambertian(Color::new(1.0, 1.0, 1.0)));
+        let coated = Material::new(MaterialType::layered(Coat::new(1.5, 0.2, Color::default(), 0.0), white));
+
+        for cos in [1.0_f64, 0.5, 0.1] {
+            let wo = Vec3::new((1.0 - cos * cos).sqrt(), 0.0, cos);
+            let sampled = albedo(&coated, wo);
+            let integrated = integrate_eval(&coated, wo);
+
+            assert!(sampled.x > 0.8 && sampled.x < 1.0, "{} at {}", sampled, cos);
+            assert!((sampled.x - integrated.x).abs() < 0.02, "{} != {} at {}", sampled, integrated, cos);
+        }
+    }
+
+    #[test]
+    fn test_layered_over_transmissive_base() {
+        // Light getting through the coat and then the glass mustn't be lost.
+        let glass = Material::new(MaterialType::Principled(Principled { transmission: 1.0, roughness: 0.3, ..Principled::new(Color::new(1.0, 1.0, 1.0)) }));
+        let coated = Material::new(MaterialType::layered(Coat::new(1.3, 0.1, Color::default(), 0.0), glass));
+
+        let sampled = albedo(&coated, Vec3::new(0.0, 0.0, 1.0));
+        assert!(sampled.x > 0.9 && sampled.x < 1.0, "{}", sampled);
+        assert!(coated.ior(None) == Some(1.5));
+    }
+
+    #[test]
+    fn test_mix_blends_eval_and_pdf() {
+        let a = Material::new(MaterialType::Lambertian(Color::new(0.8, 0.8, 0.8)));
+        let b = Material::new(MaterialType::conductor(microfacet::GOLD, 0.3));
+        let mix = Material::new(MaterialType::mix(a.clone(), b.clone(), Texture::Constant(Color::new(0.25, 0.25, 0.25))));
+
+        let (r_in, rec) = hit(mix.clone(), Vec3::new(0.6, 0.0, 0.8));
+        for direction in [Vec3::new(-0.6, 0.0, 0.8), Vec3::new(0.0, 0.6, 0.8), Vec3::new(-0.3, 0.1, 0.9)] {
+            let eval = 0.75 * a.eval(&r_in, &rec, 1.0, &Channels::Rgb, &direction) + 0.25 * b.eval(&r_in, &rec, 1.0, &Channels::Rgb, &direction);
+            assert!((mix.eval(&r_in, &rec, 1.0, &Channels::Rgb, &direction) - eval).length() < 1e-12);
+
+            let pdf = 0.75 * a.pdf(&r_in, &rec, 1.0, &Channels::Rgb, &direction) + 0.25 * b.pdf(&r_in, &rec, 1.0, &Channels::Rgb, &direction);
+            assert!((mix.pdf(&r_in, &rec, 1.0, &Channels::Rgb, &direction) - pdf).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_mix_of_cut_out_material() {
+        let a = Material::new(MaterialType::Lambertian(Color::new(0.8, 0.8, 0.8)));
+        let b = a.clone().with_alpha(Texture::Constant(Color::default()));
+        let mix = Material::new(MaterialType::mix(a.clone(), b, Texture::Constant(Color::new(0.25, 0.25, 0.25))));
+        let (_, rec) = hit(mix.clone(), Vec3::new(0.0, 0.0, 1.0));
+        assert!((mix.opacity(&rec) - 0.75).abs() < 1e-12);
+
+        // Anywhere it's hit, it's `a`.
+        let mut sampler = Sampler::new(SamplerKind::Sobol, 64);
+        for i in 0..64 {
+            sampler.start_pixel_sample(0, 0, i);
+            assert!(mix.resolve(&rec, &mut sampler) == a);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_film_needs_a_dielectric_or_conductor() {
+        let frosted = Material::new(MaterialType::RoughDielectric(Ior::Constant(1.5), Color::default(), 0.3));
+        frosted.with_film(ThinFilm::new(300.0, 1.33));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::{Material, MaterialType};
+use crate::phase::PhaseFunction;
+use crate::ray::Ray;
+use crate::utility::random_f64;
+use crate::vector::{Color, Point3, Vec3};
+use crate::voxel::VoxelGrid;
+
+
+/// Fog, smoke or mist of uniform density, filling a closed `boundary`.
+/// Rays scatter at a random distance inside, in a direction following the
+/// phase function, which is isotropic unless set.
+pub struct ConstantMedium<T> where T: Hittable {
+    boundary: T,
+    negative_inverse_density: f64,
+    albedo: Color,
+    phase_function: PhaseFunction,
+}
+
+impl<T: Hittable> ConstantMedium<T> {
+    pub fn new(boundary: T, density: f64, albedo: Color) -> Self {
+        ConstantMedium {
+            boundary,
+            negative_inverse_density: -1.0 / density,
+            albedo,
+            phase_function: PhaseFunction::Isotropic,
+        }
+    }
+
+    pub fn with_phase_function(self: Self, phase_function: PhaseFunction) -> Self {
+        ConstantMedium { phase_function, ..self }
+    }
+
+    /// The part of `t_min..t_max` the ray spends inside the boundary.
+    fn inside(self: &Self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        // Find where the ray enters and leaves the boundary, even if it starts inside.
+        let mut rec1 = HitRecord::default();
+        let mut rec2 = HitRecord::default();
+
+        if !self.boundary.hit(ray, -f64::INFINITY, f64::INFINITY, &mut rec1) { return None; }
+        if !self.boundary.hit(ray, rec1.t + 0.0001, f64::INFINITY, &mut rec2) { return None; }
+
+        let t_enter = rec1.t.max(t_min).max(0.0);
+        let t_exit = rec2.t.min(t_max);
+        if t_enter >= t_exit { return None; }
+
+        Some((t_enter, t_exit))
+    }
+}
+
+impl<T: Hittable> Hittable for ConstantMedium<T> {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let (t_enter, t_exit) = match self.inside(ray, t_min, t_max) {
+            Some(interval) => interval,
+            None => return false,
+        };
+
+        let ray_length = ray.direction.length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let hit_distance = self.negative_inverse_density * random_f64(None, None).ln();
+
+        if hit_distance > distance_inside_boundary { return false; }
+
+        rec.t = t_enter + hit_distance / ray_length;
+        rec.p = ray.at(rec.t);
+
+        // Neither of these mean anything inside a volume.
+        rec.normal = Vec3::new(1.0, 0.0, 0.0);
+        rec.front_face = true;
+        rec.material = Material::new(MaterialType::Volume(self.albedo, self.phase_function));
+
+        true
+    }
+
+    /// Exactly, as the density is constant.
+    fn transmittance(self: &Self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        match self.inside(ray, t_min, t_max) {
+            Some((t_enter, t_exit)) => ((t_exit - t_enter) * ray.direction.length() / self.negative_inverse_density).exp(),
+            None => 1.0,
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+
+/// Smoke or cloud whose density varies, following a voxel grid stretched
+/// over `bounds`. Scattering happens where delta tracking says (Woodcock et
+/// al. 1965) and shadow rays are attenuated by ratio tracking (Novák et al.
+/// 2014), both against the grid's largest density, so neither is biased.
+pub struct GridMedium {
+    grid: VoxelGrid,
+    bounds: Aabb,
+
+    /// Density where the grid's value is 1.
+    density: f64,
+    albedo: Color,
+    phase_function: PhaseFunction,
+}
+
+impl GridMedium {
+    pub fn new(grid: VoxelGrid, bounds: Aabb, density: f64, albedo: Color) -> Self {
+        GridMedium {
+            grid,
+            bounds,
+            density,
+            albedo,
+            phase_function: PhaseFunction::Isotropic,
+        }
+    }
+
+    pub fn with_phase_function(self: Self, phase_function: PhaseFunction) -> Self {
+        GridMedium { phase_function, ..self }
+    }
+
+    fn density_at(self: &Self, p: &Point3) -> f64 {
+        let extent = self.bounds.maximum - self.bounds.minimum;
+        let offset = *p - self.bounds.minimum;
+        let local = Point3::new(offset.x / extent.x, offset.y / extent.y, offset.z / extent.z);
+        self.density * self.grid.lookup(&local)
+    }
+
+    /// Distance along the ray to the next tentative collision, at the
+    /// largest density, in units of `t`.
+    fn free_flight(self: &Self, ray: &Ray, majorant: f64) -> f64 {
+        -(1.0 - random_f64(None, None)).ln() / (majorant * ray.direction.length())
+    }
+}
+
+impl Hittable for GridMedium {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let majorant = self.density * self.grid.max_value();
+        let (mut t, t_exit) = match self.bounds.hit_interval(ray, t_min, t_max) {
+            Some(interval) if majorant > 0.0 => interval,
+            _ => return false,
+        };
+
+        loop {
+            t += self.free_flight(ray, majorant);
+            if t >= t_exit {
+                return false;
+            }
+
+            // A real collision, rather than with the padding up to the majorant.
+            let p = ray.at(t);
+            if random_f64(None, None) * majorant < self.density_at(&p) {
+                rec.t = t;
+                rec.p = p;
+                rec.normal = Vec3::new(1.0, 0.0, 0.0);
+                rec.front_face = true;
+                rec.material = Material::new(MaterialType::Volume(self.albedo, self.phase_function));
+                return true;
+            }
+        }
+    }
+
+    fn transmittance(self: &Self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let majorant = self.density * self.grid.max_value();
+        let (mut t, t_exit) = match self.bounds.hit_interval(ray, t_min, t_max) {
+            Some(interval) if majorant > 0.0 => interval,
+            _ => return 1.0,
+        };
+
+        let mut transmittance = 1.0;
+        loop {
+            t += self.free_flight(ray, majorant);
+            if t >= t_exit {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.density_at(&ray.at(t)) / majorant;
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
@@ -0,0 +1,376 @@
+// Microfacet reflection: the Trowbridge-Reitz (GGX) distribution of normals
+// with Smith masking-shadowing, sampled by visible normals, and the Fresnel
+// reflectance of conductors. Directions are in a local frame around the
+// shading normal, which is `z`.
+
+use std::f64::consts::PI;
+
+use crate::spectrum::RGB_WAVELENGTHS;
+use crate::vector::{Color, Vec3};
+
+
+/// Isotropic Trowbridge-Reitz distribution.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrowbridgeReitz {
+    alpha: f64,
+}
+
+impl TrowbridgeReitz {
+    /// `roughness` in `[0, 1]` is perceptually linear; `alpha` is its square.
+    pub fn new(roughness: f64) -> Self {
+        TrowbridgeReitz { alpha: (roughness * roughness).max(1e-4) }
+    }
+
+    /// Close enough to a mirror that it should be treated as one.
+    pub fn is_smooth(self: &Self) -> bool {
+        self.alpha < 1e-3
+    }
+
+    /// Density of microfacet normals `wm`, per unit projected area.
+    pub fn d(self: &Self, wm: &Vec3) -> f64 {
+        let cos2 = wm.z * wm.z;
+        if cos2 <= 0.0 {
+            return 0.0;
+        }
+
+        let tan2 = (wm.x * wm.x + wm.y * wm.y) / cos2;
+        let e = 1.0 + tan2 / (self.alpha * self.alpha);
+        1.0 / (PI * self.alpha * self.alpha * cos2 * cos2 * e * e)
+    }
+
+    fn lambda(self: &Self, w: &Vec3) -> f64 {
+        let cos2 = w.z * w.z;
+        if cos2 <= 0.0 {
+            return f64::INFINITY;
+        }
+
+        let tan2 = (w.x * w.x + w.y * w.y) / cos2;
+        0.5 * (-1.0 + (1.0 + self.alpha * self.alpha * tan2).sqrt())
+    }
+
+    /// Fraction of microfacets visible from `w`.
+    pub fn g1(self: &Self, w: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of microfacets visible from both `wo` and `wi`.
+    pub fn g(self: &Self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Sample a microfacet normal visible from `wo`, after Heitz,
+    /// "Sampling the GGX Distribution of Visible Normals" (2018).
+    pub fn sample_visible(self: &Self, wo: &Vec3, u1: f64, u2: f64) -> Vec3 {
+        // Stretch to the hemisphere configuration.
+        let wh = Vec3::new(self.alpha * wo.x, self.alpha * wo.y, wo.z).unit_vector();
+
+        let length2 = wh.x * wh.x + wh.y * wh.y;
+        let t1 = match length2 > 0.0 {
+            true => Vec3::new(-wh.y, wh.x, 0.0) / length2.sqrt(),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let t2 = wh.cross(t1);
+
+        // A point on the disk, squashed onto the visible half.
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + wh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * wh;
+
+        // And unstretch.
+        Vec3::new(self.alpha * nh.x, self.alpha * nh.y, nh.z.max(1e-6)).unit_vector()
+    }
+
+    /// PDF of `sample_visible` choosing `wm`.
+    pub fn pdf_visible(self: &Self, wo: &Vec3, wm: &Vec3) -> f64 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(wm).max(0.0) * self.d(wm) / wo.z
+    }
+}
+
+
+/// Fresnel reflectance of a conductor with complex refractive index
+/// `eta + i k`, per channel, for light arriving at `cos_theta` to the normal.
+pub fn fresnel_conductor(cos_theta: f64, eta: Color, k: Color) -> Color {
+    let channel = |eta: f64, k: f64| {
+        let cos2 = cos_theta * cos_theta;
+        let sin2 = 1.0 - cos2;
+        let eta2 = eta * eta;
+        let k2 = k * k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_theta * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+
+        0.5 * (rp + rs)
+    };
+
+    Color::new(channel(eta.x, k.x), channel(eta.y, k.y), channel(eta.z, k.z))
+}
+
+
+/// Fresnel reflectance of a dielectric interface, where `eta` is the
+/// refractive index on the far side over that on the near side.
+pub fn fresnel_dielectric(cos_theta: f64, eta: f64) -> f64 {
+    let sin2_t = (1.0 - cos_theta * cos_theta) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_theta - cos_t) / (eta * cos_theta + cos_t);
+    let perpendicular = (cos_theta - eta * cos_t) / (cos_theta + eta * cos_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
+
+/// Refract `wo` through a microfacet with normal `wm`, unless it's totally
+/// internally reflected. `eta` is as for `fresnel_dielectric`.
+fn refract(wo: &Vec3, wm: &Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = wo.dot(wm);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-*wo / eta + (cos_i / eta - cos_t) * *wm)
+}
+
+/// Sample the rough dielectric BSDF of Walter et al., "Microfacet Models for
+/// Refraction through Rough Surfaces" (2007), for light leaving along `wo`
+/// (above the surface). `eta` is the refractive index below the surface
+/// over that above it. Returns the incoming direction, which is below the
+/// surface when transmitted, and the sample's `f cos / pdf`.
+pub fn sample_dielectric(distribution: &TrowbridgeReitz, eta: f64, wo: &Vec3, u0: f64, u1: f64, u2: f64) -> Option<(Vec3, f64)> {
+    let wm = distribution.sample_visible(wo, u1, u2);
+    let reflectance = fresnel_dielectric(wo.dot(&wm), eta);
+
+    // Pick reflection or transmission in proportion to the Fresnel terms,
+    // which then cancel out of the weight, as does most of the rest.
+    let wi = match u0 < reflectance {
+        true => -*wo + 2.0 * wo.dot(&wm) * wm,
+        false => refract(wo, &wm, eta)?,
+    };
+
+    let reflected = u0 < reflectance;
+    if (wi.z > 0.0) != reflected || wi.z == 0.0 {
+        return None;
+    }
+
+    Some((wi, distribution.g(wo, &wi) / distribution.g1(wo)))
+}
+
+/// `f cos` and the PDF of `sample_dielectric`, for light arriving from `wi`.
+pub fn eval_dielectric(distribution: &TrowbridgeReitz, eta: f64, wo: &Vec3, wi: &Vec3) -> (f64, f64) {
+    if wo.z <= 0.0 || wi.z == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    // The microfacet normal that takes one direction to the other, facing up.
+    let reflected = wi.z > 0.0;
+    let wm = match reflected {
+        true => *wo + *wi,
+        false => *wo + *wi * eta,
+    };
+    if wm.length_squared() == 0.0 {
+        return (0.0, 0.0);
+    }
+    let wm = if wm.z < 0.0 { -wm.unit_vector() } else { wm.unit_vector() };
+
+    // Neither direction can be behind the microfacet it scatters off.
+    if wo.dot(&wm) <= 0.0 || wi.dot(&wm) * wi.z <= 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let reflectance = fresnel_dielectric(wo.dot(&wm), eta);
+    let d = distribution.d(&wm);
+    let g = distribution.g(wo, wi);
+    let visible = distribution.pdf_visible(wo, &wm);
+
+    match reflected {
+        true => (
+            reflectance * d * g / (4.0 * wo.z),
+            reflectance * visible / (4.0 * wo.dot(&wm)),
+        ),
+        false => {
+            // Radiance isn't scaled by eta² on the way through, matching
+            // smooth dielectrics.
+            let denominator = (wi.dot(&wm) + wo.dot(&wm) / eta).powi(2) * eta * eta;
+            let jacobian = wi.dot(&wm).abs() * eta * eta / denominator;
+            (
+                (1.0 - reflectance) * d * g * wo.dot(&wm) * jacobian / wo.z,
+                (1.0 - reflectance) * visible * jacobian,
+            )
+        }
+    }
+}
+
+/// Oren and Nayar's rough diffuse reflection (1994), in its qualitative form,
+/// relative to Lambert's: multiply by `albedo / π` for the BRDF. `sigma` is
+/// the standard deviation of the facets' slope angle, in radians; at 0 this
+/// is always 1.
+pub fn oren_nayar(sigma: f64, wo: &Vec3, wi: &Vec3) -> f64 {
+    let sigma2 = sigma * sigma;
+    let a = 1.0 - sigma2 / (2.0 * (sigma2 + 0.33));
+    let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+    let sin_o = (1.0 - wo.z * wo.z).max(0.0).sqrt();
+    let sin_i = (1.0 - wi.z * wi.z).max(0.0).sqrt();
+    if sin_o < 1e-6 || sin_i < 1e-6 {
+        return a;
+    }
+
+    // cos(phi_i - phi_o), from the directions' projections onto the surface.
+    let cos_phi = ((wo.x * wi.x + wo.y * wi.y) / (sin_o * sin_i)).max(0.0);
+
+    // sin(alpha) tan(beta), with alpha the larger angle to the normal.
+    let (sin_alpha, tan_beta) = match wi.z < wo.z {
+        true => (sin_i, sin_o / wo.z),
+        false => (sin_o, sin_i / wi.z),
+    };
+
+    a + b * cos_phi * sin_alpha * tan_beta
+}
+
+/// The "Charlie" sheen BRDF of Estevez and Kulla, "Production Friendly
+/// Microfacet Sheen BRDF" (2017), with Neubelt and Pettineo's simpler
+/// visibility term, for the soft highlights of cloth at grazing angles.
+pub fn sheen(roughness: f64, wo: &Vec3, wi: &Vec3) -> f64 {
+    if wo.z <= 0.0 || wi.z <= 0.0 {
+        return 0.0;
+    }
+
+    let alpha = (roughness * roughness).max(1e-3);
+    let wm = (*wo + *wi).unit_vector();
+    let sin_m = (1.0 - wm.z * wm.z).max(0.0).sqrt();
+    let d = (2.0 + 1.0 / alpha) * sin_m.powf(1.0 / alpha) / (2.0 * PI);
+
+    let visibility = 1.0 / (4.0 * (wi.z + wo.z - wi.z * wo.z));
+    d * visibility
+}
+
+// Measured complex refractive indices, as (wavelength in nm, eta, k), from
+// Johnson and Christy (1972) for the noble metals and Rakić (1995) for
+// aluminum.
+
+pub const GOLD: &[(f64, f64, f64)] = &[
+    (400.0, 1.658, 1.956), (450.0, 1.500, 1.880), (500.0, 0.970, 1.870),
+    (550.0, 0.430, 2.455), (600.0, 0.250, 2.980), (650.0, 0.170, 3.460),
+    (700.0, 0.160, 3.950),
+];
+
+pub const SILVER: &[(f64, f64, f64)] = &[
+    (400.0, 0.173, 1.950), (450.0, 0.144, 2.560), (500.0, 0.130, 3.000),
+    (550.0, 0.120, 3.340), (600.0, 0.120, 3.730), (650.0, 0.140, 4.150),
+    (700.0, 0.140, 4.520),
+];
+
+pub const COPPER: &[(f64, f64, f64)] = &[
+    (400.0, 1.180, 2.210), (450.0, 1.170, 2.400), (500.0, 1.120, 2.560),
+    (550.0, 0.950, 2.580), (600.0, 0.270, 3.280), (650.0, 0.210, 3.670),
+    (700.0, 0.210, 4.050),
+];
+
+pub const ALUMINUM: &[(f64, f64, f64)] = &[
+    (400.0, 0.490, 4.860), (450.0, 0.620, 5.470), (500.0, 0.770, 6.080),
+    (550.0, 0.960, 6.690), (600.0, 1.200, 7.260), (650.0, 1.470, 7.790),
+    (700.0, 1.830, 8.310),
+];
+
+/// `eta` and `k` from a table at `RGB_WAVELENGTHS`, interpolating linearly.
+pub fn rgb_ior(table: &[(f64, f64, f64)]) -> (Color, Color) {
+    let at = |lambda: f64| {
+        let i = table.iter().position(|entry| entry.0 >= lambda).unwrap_or(table.len() - 1).max(1);
+        let (l0, eta0, k0) = table[i - 1];
+        let (l1, eta1, k1) = table[i];
+        let t = ((lambda - l0) / (l1 - l0)).clamp(0.0, 1.0);
+        (eta0 + t * (eta1 - eta0), k0 + t * (k1 - k0))
+    };
+
+    let [red, green, blue] = RGB_WAVELENGTHS.map(at);
+    (Color::new(red.0, green.0, blue.0), Color::new(red.1, green.1, blue.1))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distribution_is_normalized() {
+        // The projected area of the microfacets is that of the surface.
+        for roughness in [0.3, 0.6, 0.9] {
+            let distribution = TrowbridgeReitz::new(roughness);
+            let n = 100_000;
+
+            // It's isotropic, so integrate over cos(theta) alone.
+            let integral: f64 = (0..n)
+                .map(|i| {
+                    let cos_theta = (i as f64 + 0.5) / n as f64;
+                    let wm = Vec3::new((1.0 - cos_theta * cos_theta).sqrt(), 0.0, cos_theta);
+                    distribution.d(&wm) * cos_theta
+                })
+                .sum::<f64>() * 2.0 * PI / n as f64;
+
+            assert!((integral - 1.0).abs() < 0.01, "{} at roughness {}", integral, roughness);
+        }
+    }
+
+    #[test]
+    fn test_fresnel_conductor_at_normal_incidence() {
+        let (eta, k) = (Color::new(0.2, 0.9, 1.1), Color::new(3.9, 2.4, 2.1));
+        let reflectance = fresnel_conductor(1.0, eta, k);
+
+        for i in 0..3 {
+            let expected = ((eta[i] - 1.0).powi(2) + k[i] * k[i]) / ((eta[i] + 1.0).powi(2) + k[i] * k[i]);
+            assert!((reflectance[i] - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_oren_nayar() {
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let wi = Vec3::new(-0.3, 0.4, (1.0 - 0.25_f64).sqrt());
+        assert_eq!(oren_nayar(0.0, &wo, &wi), 1.0);
+
+        // Rough surfaces scatter more back towards the light.
+        let back = Vec3::new(0.6, 0.0, 0.8);
+        let forward = Vec3::new(-0.6, 0.0, 0.8);
+        assert!(oren_nayar(0.5, &wo, &back) > oren_nayar(0.5, &wo, &forward));
+    }
+
+    #[test]
+    fn test_sample_dielectric_matches_eval() {
+        let distribution = TrowbridgeReitz::new(0.5);
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+
+        for eta in [1.5, 1.0 / 1.5] {
+            let mut count = 0;
+            for i in 0..20 {
+                for j in 0..20 {
+                    let u = ((i as f64 + 0.5) / 20.0, (j as f64 + 0.5) / 20.0);
+                    for u0 in [0.01, 0.5, 0.99] {
+                        if let Some((wi, weight)) = sample_dielectric(&distribution, eta, &wo, u0, u.0, u.1) {
+                            let (f, pdf) = eval_dielectric(&distribution, eta, &wo, &wi);
+                            assert!((f / pdf - weight).abs() < 1e-6, "{} != {} for {}", f / pdf, weight, wi);
+                            count += 1;
+                        }
+                    }
+                }
+            }
+            assert!(count > 1000);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::vector::Vec3;
+
+/// An orthonormal basis, used to move directions in and out of a frame
+/// built around a single vector (usually a normal).
+#[derive(Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Build a basis whose `w` axis points along `n`.
+    pub fn build_from_w(n: &Vec3) -> Self {
+        let w = n.unit_vector();
+        let a = match w.x.abs() > 0.9 {
+            true => Vec3::new(0.0, 1.0, 0.0),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let v = w.cross(a).unit_vector();
+        let u = w.cross(v);
+
+        Onb { u, v, w }
+    }
+
+    /// Local co-ordinates to world space.
+    pub fn local(self: &Self, a: f64, b: f64, c: f64) -> Vec3 {
+        a * self.u + b * self.v + c * self.w
+    }
+
+    /// World space to local co-ordinates.
+    pub fn to_local(self: &Self, a: &Vec3) -> Vec3 {
+        Vec3::new(a.dot(&self.u), a.dot(&self.v), a.dot(&self.w))
+    }
+}
@@ -0,0 +1,106 @@
+use std::f64::consts::PI;
+
+use crate::onb::Onb;
+use crate::vector::Vec3;
+
+
+/// How participating media spread light out as it scatters, as a density
+/// over the angle between the directions of travel before and after. Each
+/// is importance sampled exactly, so the PDF of `sample` is `eval`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PhaseFunction {
+    Isotropic,
+
+    /// HenyeyGreenstein(g). `g` in `(-1, 1)` is the average cosine of the
+    /// scattering angle: positive scatters forward, as in clouds, negative
+    /// backward.
+    HenyeyGreenstein(f64),
+
+    /// DoubleHenyeyGreenstein(g_forward, g_backward, forward_weight). A blend
+    /// of two lobes, for media with a strong forward peak and a little
+    /// backscatter.
+    DoubleHenyeyGreenstein(f64, f64, f64),
+}
+
+impl PhaseFunction {
+    /// Density of scattering by the angle with cosine `cos_theta`.
+    pub fn eval(self: &Self, cos_theta: f64) -> f64 {
+        match *self {
+            PhaseFunction::Isotropic => 1.0 / (4.0 * PI),
+            PhaseFunction::HenyeyGreenstein(g) => henyey_greenstein(g, cos_theta),
+            PhaseFunction::DoubleHenyeyGreenstein(g_forward, g_backward, w) => {
+                w * henyey_greenstein(g_forward, cos_theta) + (1.0 - w) * henyey_greenstein(g_backward, cos_theta)
+            }
+        }
+    }
+
+    /// A new direction of travel, for light going along `forward`, from
+    /// numbers in `[0, 1)`: `u0` picks a lobe, and `u` a direction in it.
+    pub fn sample(self: &Self, forward: &Vec3, u0: f64, u: (f64, f64)) -> Vec3 {
+        let g = match *self {
+            PhaseFunction::Isotropic => 0.0,
+            PhaseFunction::HenyeyGreenstein(g) => g,
+            PhaseFunction::DoubleHenyeyGreenstein(g_forward, g_backward, w) => match u0 < w {
+                true => g_forward,
+                false => g_backward,
+            },
+        };
+
+        let (u1, u2) = u;
+        let cos_theta = match g.abs() < 1e-3 {
+            true => 1.0 - 2.0 * u1,
+            false => {
+                let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u1);
+                ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+            }
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+
+        Onb::build_from_w(forward).local(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+    }
+}
+
+
+fn henyey_greenstein(g: f64, cos_theta: f64) -> f64 {
+    let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denominator * denominator.max(1e-12).sqrt())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::random_f64;
+
+    #[test]
+    fn test_phase_functions_are_normalized() {
+        let phases = [
+            PhaseFunction::Isotropic,
+            PhaseFunction::HenyeyGreenstein(0.7),
+            PhaseFunction::HenyeyGreenstein(-0.4),
+            PhaseFunction::DoubleHenyeyGreenstein(0.8, -0.3, 0.9),
+        ];
+
+        for phase in phases {
+            // Symmetric about the forward direction, so integrate over cos(theta) alone.
+            let n = 100_000;
+            let integral: f64 = (0..n)
+                .map(|i| phase.eval(-1.0 + 2.0 * (i as f64 + 0.5) / n as f64))
+                .sum::<f64>() * 2.0 * PI * 2.0 / n as f64;
+
+            assert!((integral - 1.0).abs() < 1e-3, "{:?} integrates to {}", phase, integral);
+        }
+    }
+
+    #[test]
+    fn test_henyey_greenstein_mean_cosine() {
+        let forward = Vec3::new(0.0, 0.6, 0.8);
+        let n = 200_000;
+        let mean: f64 = (0..n).map(|_| {
+                let u = (random_f64(None, None), random_f64(None, None));
+                PhaseFunction::HenyeyGreenstein(0.6).sample(&forward, 0.0, u).dot(&forward)
+            }).sum::<f64>() / n as f64;
+        assert!((mean - 0.6).abs() < 0.01, "{}", mean);
+    }
+}
@@ -0,0 +1,156 @@
+use std::f64::consts::PI;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vector::{Point3, Vec3};
+
+
+/// An infinite plane through `point`. The front face is the one `normal`
+/// points out of.
+#[derive(Clone)]
+pub struct Plane {
+    point: Point3,
+    normal: Vec3,
+    material: Material,
+
+    /// Axes along the plane, used for UVs.
+    frame: Onb,
+
+    /// World space size of one `[0, 1)` UV tile.
+    uv_scale: f64,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, material: Material) -> Self {
+        Plane::with_uv_scale(point, normal, 1.0, material)
+    }
+
+    /// UVs repeat every `uv_scale` units along the plane.
+    pub fn with_uv_scale(point: Point3, normal: Vec3, uv_scale: f64, material: Material) -> Self {
+        let normal = normal.unit_vector();
+
+        Plane {
+            point,
+            normal,
+            material,
+            frame: Onb::build_from_w(&normal),
+            uv_scale,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let t = match intersect_plane(ray, &self.point, &self.normal) {
+            Some(t) => t,
+            None => return false,
+        };
+        if t < t_min || t > t_max { return false; }
+
+        rec.t = t;
+        rec.p = ray.at(t);
+
+        let planar = rec.p - self.point;
+        rec.u = (planar.dot(&self.frame.u) / self.uv_scale).rem_euclid(1.0);
+        rec.v = (planar.dot(&self.frame.v) / self.uv_scale).rem_euclid(1.0);
+        rec.dpdu = self.frame.u * self.uv_scale;
+        rec.dpdv = self.frame.v * self.uv_scale;
+
+        rec.set_face_normal(ray, &self.normal);
+        rec.material = self.material.clone();
+
+        true
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        None
+    }
+}
+
+
+/// A flat, round disk. The front face is the one `normal` points out of.
+#[derive(Clone)]
+pub struct Disk {
+    center: Point3,
+    normal: Vec3,
+    radius: f64,
+    material: Material,
+    frame: Onb,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, material: Material) -> Self {
+        let normal = normal.unit_vector();
+
+        Disk {
+            center,
+            normal,
+            radius,
+            material,
+            frame: Onb::build_from_w(&normal),
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let t = match intersect_plane(ray, &self.center, &self.normal) {
+            Some(t) => t,
+            None => return false,
+        };
+        if t < t_min || t > t_max { return false; }
+
+        let p = ray.at(t);
+        let planar = p - self.center;
+        let distance_squared = planar.length_squared();
+        if distance_squared > self.radius * self.radius { return false; }
+
+        rec.t = t;
+        rec.p = p;
+
+        // Polar co-ordinates: `u` around the disk, `v` out from the center.
+        let (x, y) = (planar.dot(&self.frame.u), planar.dot(&self.frame.v));
+        let phi = y.atan2(x);
+        let rho = distance_squared.sqrt();
+        rec.u = (phi / (2.0 * PI)).rem_euclid(1.0);
+        rec.v = rho / self.radius;
+
+        // Neither is defined at the very center.
+        rec.dpdu = 2.0 * PI * (x * self.frame.v - y * self.frame.u);
+        rec.dpdv = match rho > 0.0 {
+            true => self.radius / rho * planar,
+            false => Vec3::default(),
+        };
+
+        rec.set_face_normal(ray, &self.normal);
+        rec.material = self.material.clone();
+
+        true
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        // How far the rim reaches along each axis.
+        let n = self.normal;
+        let extent = self.radius * Vec3::new(
+            (1.0 - n.x * n.x).max(0.0).sqrt(),
+            (1.0 - n.y * n.y).max(0.0).sqrt(),
+            (1.0 - n.z * n.z).max(0.0).sqrt(),
+        );
+
+        Some(Aabb::new(self.center - extent, self.center + extent).pad(0.0001))
+    }
+}
+
+/// Ray parameter where `ray` meets the plane through `point`, if it isn't
+/// parallel to it.
+fn intersect_plane(ray: &Ray, point: &Point3, normal: &Vec3) -> Option<f64> {
+    let denominator = normal.dot(&ray.direction);
+    if denominator.abs() < 1e-8 {
+        return None;
+    }
+
+    Some((*point - ray.origin).dot(normal) / denominator)
+}
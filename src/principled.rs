@@ -0,0 +1,353 @@
+// A principled BSDF in the spirit of Burley, "Physically Based Shading at
+// Disney" (2012) and "Extending the Disney BRDF to a BSDF with Integrated
+// Subsurface Scattering" (2015): one set of artist friendly parameters
+// covering diffuse, metals, plastics and glass. Directions are in a local
+// frame around the shading normal, which is `z`.
+
+use std::error;
+use std::f64::consts::PI;
+use std::fs;
+
+use crate::microfacet::{eval_dielectric, sample_dielectric, TrowbridgeReitz};
+use crate::vector::{Color, Vec3};
+
+/// Let's Box any errors!
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+
+/// Every weight is in `[0, 1]`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Principled {
+    pub base_color: Color,
+    pub metallic: f64,
+    pub roughness: f64,
+
+    /// Strength of the specular highlight on non-metals; 0.5 is the usual
+    /// 4% reflectance at normal incidence.
+    pub specular: f64,
+
+    /// Soft, velvety reflection towards grazing angles, for cloth. Tinted
+    /// towards the base color by `sheen_tint`.
+    pub sheen: f64,
+    pub sheen_tint: f64,
+
+    /// A second, colorless specular layer, like lacquer.
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
+
+    /// How much light passes through rather than being diffused, as glass.
+    pub transmission: f64,
+    pub ior: f64,
+
+    /// Flattens the diffuse lobe, approximating light bleeding under the
+    /// surface.
+    pub subsurface: f64,
+}
+
+impl Principled {
+    /// A rough dielectric of `base_color`, with everything else off.
+    pub fn new(base_color: Color) -> Self {
+        Principled {
+            base_color,
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            transmission: 0.0,
+            ior: 1.5,
+            subsurface: 0.0,
+        }
+    }
+
+    /// From the core of glTF's metallic-roughness model. None of its
+    /// `KHR_materials_*` extensions are read; set the other fields after.
+    pub fn from_gltf(base_color_factor: Color, metallic_factor: f64, roughness_factor: f64) -> Self {
+        Principled {
+            metallic: metallic_factor,
+            roughness: roughness_factor,
+            ..Principled::new(base_color_factor)
+        }
+    }
+
+    /// Every material in a Wavefront `.mtl` file, by name. Understands the
+    /// classic `Kd`, `Ks`, `Ns`, `Ni`, `d` and `Tr` statements, and the PBR
+    /// extension's `Pr`, `Pm`, `Ps`, `Pc` and `Pcr`, which win where both
+    /// are given. Colors are taken to be linear.
+    pub fn from_mtl(source: &str) -> Result<Vec<(String, Principled)>> {
+        let mut materials: Vec<(String, Principled)> = vec![];
+        let mut explicit_roughness = false;
+
+        for (number, line) in source.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) if !keyword.starts_with('#') => keyword,
+                _ => continue,
+            };
+
+            if keyword == "newmtl" {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                materials.push((name, Principled::new(Color::new(0.8, 0.8, 0.8))));
+                explicit_roughness = false;
+                continue;
+            }
+
+            let values = tokens.map(|t| t.parse::<f64>()).collect::<std::result::Result<Vec<f64>, _>>();
+            let values = match values {
+                Ok(values) => values,
+                // Texture maps and the like.
+                Err(_) => continue,
+            };
+            let material = match materials.last_mut() {
+                Some((_, material)) => material,
+                None => return Err(format!("Line {}: `{}` before any `newmtl`", number + 1, keyword).into()),
+            };
+
+            let value = *values.first().ok_or_else(|| format!("Line {}: `{}` needs a value", number + 1, keyword))?;
+            let color = match values.len() {
+                3 => Color::new(values[0], values[1], values[2]),
+                _ => Color::new(value, value, value),
+            };
+
+            match keyword {
+                "Kd" => material.base_color = color,
+                // The 4% of an ordinary dielectric is a specular of 0.5.
+                "Ks" => material.specular = (color.luminance() / 0.08).min(1.0),
+                // Phong exponent to GGX, by way of Beckmann (Walter et al. 2007).
+                "Ns" if !explicit_roughness => material.roughness = (2.0 / (value + 2.0)).sqrt().sqrt(),
+                "Ni" => material.ior = value,
+                "d" => material.transmission = 1.0 - value,
+                "Tr" => material.transmission = value,
+                "Pr" => {
+                    material.roughness = value;
+                    explicit_roughness = true;
+                }
+                "Pm" => material.metallic = value,
+                "Ps" => material.sheen = value,
+                "Pc" => material.clearcoat = value,
+                "Pcr" => material.clearcoat_roughness = value,
+                _ => {}
+            }
+        }
+
+        Ok(materials)
+    }
+
+    /// Load every material in a `.mtl` file, as `from_mtl`.
+    pub fn load_mtl(path: &str) -> Result<Vec<(String, Principled)>> {
+        Principled::from_mtl(&fs::read_to_string(path)?)
+    }
+
+    /// Probabilities of sampling the diffuse, specular, glass and clearcoat
+    /// lobes, roughly in proportion to how much each reflects.
+    fn lobe_probabilities(self: &Self) -> [f64; 4] {
+        let glass = (1.0 - self.metallic) * self.transmission;
+        let weights = [
+            (1.0 - self.metallic) * (1.0 - self.transmission) * self.base_color.luminance(),
+            (1.0 - glass) * self.specular_color().luminance().max(0.25),
+            glass,
+            0.25 * self.clearcoat,
+        ];
+
+        let total: f64 = weights.iter().sum();
+        match total > 0.0 {
+            true => weights.map(|w| w / total),
+            false => [1.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Reflectance at normal incidence of the specular lobe.
+    fn specular_color(self: &Self) -> Color {
+        let dielectric = 0.08 * self.specular;
+        lerp(Color::new(dielectric, dielectric, dielectric), self.base_color, self.metallic)
+    }
+
+    /// Pick a direction for light to arrive from, given it leaves along
+    /// `wo`. `eta` is the refractive index below the surface over that above
+    /// it, for transmission.
+    pub fn sample(self: &Self, eta: f64, wo: &Vec3, u: [f64; 3]) -> Option<Vec3> {
+        let [diffuse, specular, glass, _] = self.lobe_probabilities();
+
+        // The first number picks the lobe, and is then stretched back out
+        // for reuse.
+        let wi = if u[0] < diffuse {
+            cosine_hemisphere(u[1], u[2])
+        } else if u[0] < diffuse + specular {
+            reflect(wo, &TrowbridgeReitz::new(self.roughness).sample_visible(wo, u[1], u[2]))
+        } else if u[0] < diffuse + specular + glass {
+            let u0 = (u[0] - diffuse - specular) / glass;
+            sample_dielectric(&TrowbridgeReitz::new(self.roughness), eta, wo, u0, u[1], u[2])?.0
+        } else {
+            reflect(wo, &TrowbridgeReitz::new(self.clearcoat_roughness).sample_visible(wo, u[1], u[2]))
+        };
+
+        Some(wi)
+    }
+
+    /// BSDF times cosine for light arriving from `wi` and leaving along
+    /// `wo`, and the PDF of `sample` choosing `wi`.
+    pub fn eval(self: &Self, eta: f64, wo: &Vec3, wi: &Vec3) -> (Color, f64) {
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return (Color::default(), 0.0);
+        }
+
+        let [p_diffuse, p_specular, p_glass, p_clearcoat] = self.lobe_probabilities();
+        let glass_weight = (1.0 - self.metallic) * self.transmission;
+        let distribution = TrowbridgeReitz::new(self.roughness);
+
+        let mut f = Color::default();
+        let mut pdf = 0.0;
+
+        // Glass transmits as well as reflects.
+        if glass_weight > 0.0 {
+            let (glass_f, glass_pdf) = eval_dielectric(&distribution, eta, wo, wi);
+            let tint = if wi.z < 0.0 { self.base_color } else { Color::new(1.0, 1.0, 1.0) };
+            f += tint * (glass_weight * glass_f);
+            pdf += p_glass * glass_pdf;
+        }
+
+        if wi.z < 0.0 {
+            return (f, pdf);
+        }
+
+        let wm = (*wo + *wi).unit_vector();
+        let cos_d = wi.dot(&wm);
+
+        // Burley's diffuse, with its retro-reflection, blended towards the
+        // flatter Hanrahan-Krueger style subsurface approximation.
+        let diffuse_weight = (1.0 - self.metallic) * (1.0 - self.transmission);
+        if diffuse_weight > 0.0 {
+            let (f_in, f_out) = (schlick_weight(wi.z), schlick_weight(wo.z));
+
+            let fd90 = 0.5 + 2.0 * cos_d * cos_d * self.roughness;
+            let diffuse = (1.0 + (fd90 - 1.0) * f_in) * (1.0 + (fd90 - 1.0) * f_out);
+
+            let fss90 = cos_d * cos_d * self.roughness;
+            let fss = (1.0 + (fss90 - 1.0) * f_in) * (1.0 + (fss90 - 1.0) * f_out);
+            let subsurface = 1.25 * (fss * (1.0 / (wi.z + wo.z) - 0.5) + 0.5);
+
+            let lambert = diffuse + (subsurface - diffuse) * self.subsurface;
+            f += self.base_color * (diffuse_weight * lambert * wi.z / PI);
+
+            let tint = match self.base_color.luminance() > 0.0 {
+                true => self.base_color / self.base_color.luminance(),
+                false => Color::new(1.0, 1.0, 1.0),
+            };
+            let sheen_color = lerp(Color::new(1.0, 1.0, 1.0), tint, self.sheen_tint);
+            f += sheen_color * (diffuse_weight * self.sheen * schlick_weight(cos_d) * wi.z);
+
+            pdf += p_diffuse * wi.z / PI;
+        }
+
+        // D F G / (4 cos_o cos_i), times cos_i.
+        let specular_weight = 1.0 - glass_weight;
+        if specular_weight > 0.0 {
+            let fresnel = schlick(self.specular_color(), wo.dot(&wm));
+            let microfacet = distribution.d(&wm) * distribution.g(wo, wi) / (4.0 * wo.z);
+            f += fresnel * (specular_weight * microfacet);
+            pdf += p_specular * distribution.pdf_visible(wo, &wm) / (4.0 * wo.dot(&wm));
+        }
+
+        if self.clearcoat > 0.0 {
+            let coat = TrowbridgeReitz::new(self.clearcoat_roughness);
+            let fresnel = schlick(Color::new(0.04, 0.04, 0.04), wo.dot(&wm));
+            let microfacet = coat.d(&wm) * coat.g(wo, wi) / (4.0 * wo.z);
+            f += fresnel * (0.25 * self.clearcoat * microfacet);
+            pdf += p_clearcoat * coat.pdf_visible(wo, &wm) / (4.0 * wo.dot(&wm));
+        }
+
+        (f, pdf)
+    }
+}
+
+
+fn lerp(a: Color, b: Color, t: f64) -> Color {
+    (1.0 - t) * a + t * b
+}
+
+fn schlick_weight(cos_theta: f64) -> f64 {
+    (1.0 - cos_theta).clamp(0.0, 1.0).powi(5)
+}
+
+fn schlick(f0: Color, cos_theta: f64) -> Color {
+    f0 + (Color::new(1.0, 1.0, 1.0) - f0) * schlick_weight(cos_theta)
+}
+
+fn reflect(wo: &Vec3, wm: &Vec3) -> Vec3 {
+    -*wo + 2.0 * wo.dot(wm) * *wm
+}
+
+fn cosine_hemisphere(u1: f64, u2: f64) -> Vec3 {
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - u1).max(0.0).sqrt())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_mtl() {
+        let source = "# exported\nnewmtl red plastic\nKd 0.8 0.1 0.1\nKs 0.04 0.04 0.04\nNs 100\nmap_Kd red.png\n\nnewmtl gold\nKd 1.0 0.77 0.34\nPm 1\nPr 0.2\nNs 10\n";
+        let materials = Principled::from_mtl(source).unwrap();
+
+        assert_eq!(materials.len(), 2);
+        let (name, plastic) = &materials[0];
+        assert_eq!(name, "red plastic");
+        assert_eq!(plastic.base_color, Color::new(0.8, 0.1, 0.1));
+        assert!((plastic.specular - 0.5).abs() < 1e-9);
+        assert!(plastic.roughness > 0.3 && plastic.roughness < 0.4);
+
+        let (_, gold) = &materials[1];
+        assert_eq!((gold.metallic, gold.roughness), (1.0, 0.2));
+    }
+
+    #[test]
+    fn test_sample_matches_eval() {
+        // Sampling by `sample` and weighting by `eval` should estimate the
+        // same reflectance as spreading directions evenly over the sphere.
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let base = Principled::new(Color::new(0.8, 0.5, 0.3));
+        let materials = [
+            base,
+            Principled { transmission: 0.5, roughness: 0.4, ..base },
+            Principled { clearcoat: 1.0, clearcoat_roughness: 0.3, ..base },
+            Principled { metallic: 0.5, sheen: 1.0, subsurface: 0.5, ..base },
+        ];
+
+        let n = 100;
+        for material in materials {
+            let mut sampled = Color::default();
+            let mut uniform = Color::default();
+            for i in 0..n {
+                for j in 0..n {
+                    let (u1, u2) = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+
+                    for k in 0..4 {
+                        let u0 = (k as f64 + (i * n + j) as f64 / (n * n) as f64) / 4.0;
+                        if let Some(wi) = material.sample(1.5, &wo, [u0, u1, u2]) {
+                            let (f, pdf) = material.eval(1.5, &wo, &wi);
+                            if pdf > 0.0 {
+                                sampled += f / (pdf * 4.0 * (n * n) as f64);
+                            }
+                        }
+                    }
+
+                    let z = 1.0 - 2.0 * u1;
+                    let r = (1.0 - z * z).sqrt();
+                    let wi = Vec3::new(r * (2.0 * PI * u2).cos(), r * (2.0 * PI * u2).sin(), z);
+                    let (f, _) = material.eval(1.5, &wo, &wi);
+                    uniform += f * (4.0 * PI / (n * n) as f64);
+                }
+            }
+
+            for c in 0..3 {
+                assert!((sampled[c] - uniform[c]).abs() < 0.03, "{} != {} for {:?}", sampled, uniform, material);
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, Hittables};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vector::{Point3, Vec3};
+
+
+/// A parallelogram with a corner at `q` and sides `u` and `v`.
+/// The front face is the one `u x v` points out of.
+#[derive(Clone)]
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+
+    /// `n / (n . n)` for the plane normal `n = u x v`. Used to find the
+    /// planar co-ordinates of a hit.
+    w: Vec3,
+
+    normal: Vec3,
+
+    /// Plane constant, from `normal . p = d`.
+    d: f64,
+
+    area: f64,
+    material: Material,
+    bbox: Aabb,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, material: Material) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit_vector();
+
+        let bbox = Aabb::surrounding_box(&Aabb::new(q, q + u + v), &Aabb::new(q + u, q + v)).pad(0.0001);
+
+        Quad {
+            q,
+            u,
+            v,
+            w: n / n.dot(&n),
+            normal,
+            d: normal.dot(&q),
+            area: n.length(),
+            material,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let denominator = self.normal.dot(&ray.direction);
+
+        // Parallel to the plane.
+        if denominator.abs() < 1e-8 { return false; }
+
+        let t = (self.d - self.normal.dot(&ray.origin)) / denominator;
+        if t < t_min || t > t_max { return false; }
+
+        let p = ray.at(t);
+        let planar_hit = p - self.q;
+        let alpha = self.w.dot(&planar_hit.cross(self.v));
+        let beta = self.w.dot(&self.u.cross(planar_hit));
+
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) { return false; }
+
+        rec.t = t;
+        rec.p = p;
+        rec.u = alpha;
+        rec.v = beta;
+        rec.dpdu = self.u;
+        rec.dpdv = self.v;
+        rec.set_face_normal(ray, &self.normal);
+        rec.material = self.material.clone();
+
+        true
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+
+    fn pdf_value(self: &Self, origin: &Point3, direction: &Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        if !self.hit(&Ray::new(*origin, *direction), 0.001, f64::INFINITY, &mut rec) {
+            return 0.0;
+        }
+
+        // Convert the uniform area density to solid angle.
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (direction.dot(&self.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(self: &Self, origin: &Point3, u: (f64, f64)) -> Vec3 {
+        let p = self.q + (u.0 * self.u) + (u.1 * self.v);
+        p - *origin
+    }
+}
+
+
+/// A box spanning two opposite corners, made of six quads.
+pub struct Cuboid {
+    sides: Hittables<Quad>,
+}
+
+impl Cuboid {
+    pub fn new(a: Point3, b: Point3, material: Material) -> Self {
+        let min = Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+        let max = Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+
+        let dx = Vec3::new(max.x - min.x, 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y - min.y, 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z - min.z);
+
+        // Every side faces outwards.
+        let mut sides = Hittables::new(Quad::new(Point3::new(min.x, min.y, max.z), dx, dy, material.clone())); // front
+        sides.add(Quad::new(Point3::new(max.x, min.y, max.z), -dz, dy, material.clone())); // right
+        sides.add(Quad::new(Point3::new(max.x, min.y, min.z), -dx, dy, material.clone())); // back
+        sides.add(Quad::new(Point3::new(min.x, min.y, min.z), dz, dy, material.clone())); // left
+        sides.add(Quad::new(Point3::new(min.x, max.y, max.z), dx, -dz, material.clone())); // top
+        sides.add(Quad::new(Point3::new(min.x, min.y, min.z), dx, dz, material)); // bottom
+
+        Cuboid { sides }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.sides.hit(ray, t_min, t_max, rec)
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        self.sides.bounding_box()
+    }
+}
@@ -0,0 +1,548 @@
+// Analytic cylinders, cones, paraboloids and tori. Each one is intersected in
+// its own local space, where its axis is `+z` and its base sits at the origin.
+
+use std::f64::consts::PI;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::solver::{solve_quadratic, solve_quartic};
+use crate::vector::{Point3, Vec3};
+
+
+/// Position and orientation of a shape's local space in the world.
+#[derive(Copy, Clone)]
+struct Placement {
+    origin: Point3,
+    frame: Onb,
+}
+
+impl Placement {
+    fn new(origin: Point3, axis: &Vec3) -> Self {
+        Placement {
+            origin,
+            frame: Onb::build_from_w(axis),
+        }
+    }
+
+    /// The frame is orthonormal, so `t` means the same in both spaces.
+    fn to_local(self: &Self, ray: &Ray) -> Ray {
+        Ray::new(self.frame.to_local(&(ray.origin - self.origin)), self.frame.to_local(&ray.direction))
+    }
+
+    /// World space box around the local space box from `min` to `max`.
+    fn bounding_box(self: &Self, min: Point3, max: Point3) -> Aabb {
+        let mut bbox: Option<Aabb> = None;
+
+        for i in 0..8 {
+            let x = if i & 1 == 0 { min.x } else { max.x };
+            let y = if i & 2 == 0 { min.y } else { max.y };
+            let z = if i & 4 == 0 { min.z } else { max.z };
+            let corner = self.origin + self.frame.local(x, y, z);
+
+            let corner_box = Aabb::new(corner, corner);
+            bbox = Some(match bbox {
+                Some(b) => Aabb::surrounding_box(&b, &corner_box),
+                None => corner_box,
+            });
+        }
+
+        bbox.unwrap().pad(0.0001)
+    }
+
+    /// Fill in `rec` for a hit found in local space.
+    fn record(self: &Self, ray: &Ray, hit: &LocalHit, material: Material, rec: &mut HitRecord) {
+        rec.t = hit.t;
+        rec.p = ray.at(hit.t);
+        rec.u = hit.u;
+        rec.v = hit.v;
+
+        let outward_normal = self.frame.local(hit.normal.x, hit.normal.y, hit.normal.z).unit_vector();
+        rec.set_face_normal(ray, &outward_normal);
+        rec.dpdu = self.frame.local(hit.dpdu.x, hit.dpdu.y, hit.dpdu.z);
+        rec.dpdv = self.frame.local(hit.dpdv.x, hit.dpdv.y, hit.dpdv.z);
+        rec.material = material;
+    }
+}
+
+
+/// A candidate hit in local space. The normal needn't be normalized.
+struct LocalHit {
+    t: f64,
+    normal: Vec3,
+    u: f64,
+    v: f64,
+
+    /// How the point moves with `u` and `v`. Zero where they aren't
+    /// defined, on the axis.
+    dpdu: Vec3,
+    dpdv: Vec3,
+}
+
+/// Keep `candidate` if it's in range and closer than what we have.
+fn keep_closest(closest: &mut Option<LocalHit>, candidate: LocalHit, t_min: f64, t_max: f64) {
+    if candidate.t < t_min || candidate.t > t_max {
+        return;
+    }
+    if closest.as_ref().is_none_or(|c| candidate.t < c.t) {
+        *closest = Some(candidate);
+    }
+}
+
+/// Angle around the local `z` axis, as a `u` co-ordinate.
+fn azimuth_u(p: &Point3) -> f64 {
+    (p.y.atan2(p.x) / (2.0 * PI)).rem_euclid(1.0)
+}
+
+/// How `p` moves with `azimuth_u`.
+fn azimuth_dpdu(p: &Point3) -> Vec3 {
+    2.0 * PI * Vec3::new(-p.y, p.x, 0.0)
+}
+
+/// Unit vector away from the local `z` axis, and the distance from it.
+/// Zero on the axis.
+fn radial(p: &Point3) -> (Vec3, f64) {
+    let rho = (p.x * p.x + p.y * p.y).sqrt();
+    match rho < 1e-12 {
+        true => (Vec3::default(), rho),
+        false => (Vec3::new(p.x / rho, p.y / rho, 0.0), rho),
+    }
+}
+
+/// Roots of `a t^2 + b t + c`, falling back to the linear case when the
+/// ray runs parallel to the surface's quadratic term.
+fn quadric_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() > 1e-12 {
+        solve_quadratic(a, b, c)
+    } else if b.abs() > 1e-12 {
+        vec![-c / b]
+    } else {
+        vec![]
+    }
+}
+
+/// A flat cap at height `z`, facing along `normal_z` (`1.0` or `-1.0`).
+fn hit_cap(local: &Ray, z: f64, radius: f64, normal_z: f64) -> Option<LocalHit> {
+    if local.direction.z.abs() < 1e-12 {
+        return None;
+    }
+
+    let t = (z - local.origin.z) / local.direction.z;
+    let p = local.at(t);
+    let rho_squared = p.x * p.x + p.y * p.y;
+    if rho_squared > radius * radius {
+        return None;
+    }
+
+    Some(LocalHit {
+        t,
+        normal: Vec3::new(0.0, 0.0, normal_z),
+        u: azimuth_u(&p),
+        v: rho_squared.sqrt() / radius,
+        dpdu: azimuth_dpdu(&p),
+        dpdv: radius * radial(&p).0,
+    })
+}
+
+
+/// A cylinder from `base` along `axis`, whose length is the height.
+/// Uncapped cylinders are open tubes.
+pub struct Cylinder {
+    placement: Placement,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    material: Material,
+}
+
+impl Cylinder {
+    pub fn new(base: Point3, axis: Vec3, radius: f64, capped: bool, material: Material) -> Self {
+        Cylinder {
+            placement: Placement::new(base, &axis),
+            radius,
+            height: axis.length(),
+            capped,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let local = self.placement.to_local(ray);
+        let (o, d) = (local.origin, local.direction);
+        let mut closest: Option<LocalHit> = None;
+
+        // x^2 + y^2 = r^2
+        let a = d.x * d.x + d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.y * d.y);
+        let c = o.x * o.x + o.y * o.y - self.radius * self.radius;
+
+        for t in quadric_roots(a, b, c) {
+            let p = local.at(t);
+            if (0.0..=self.height).contains(&p.z) {
+                let hit = LocalHit {
+                    t,
+                    normal: Vec3::new(p.x, p.y, 0.0),
+                    u: azimuth_u(&p),
+                    v: p.z / self.height,
+                    dpdu: azimuth_dpdu(&p),
+                    dpdv: Vec3::new(0.0, 0.0, self.height),
+                };
+                keep_closest(&mut closest, hit, t_min, t_max);
+            }
+        }
+
+        if self.capped {
+            for (z, normal_z) in [(0.0, -1.0), (self.height, 1.0)] {
+                if let Some(cap) = hit_cap(&local, z, self.radius, normal_z) {
+                    keep_closest(&mut closest, cap, t_min, t_max);
+                }
+            }
+        }
+
+        match closest {
+            Some(hit) => { self.placement.record(ray, &hit, self.material.clone(), rec); true }
+            None => false,
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        let r = self.radius;
+        Some(self.placement.bounding_box(Point3::new(-r, -r, 0.0), Point3::new(r, r, self.height)))
+    }
+}
+
+
+/// A cone with a round base of `radius` at `base`, narrowing to its apex at
+/// `base + axis`.
+pub struct Cone {
+    placement: Placement,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    material: Material,
+}
+
+impl Cone {
+    pub fn new(base: Point3, axis: Vec3, radius: f64, capped: bool, material: Material) -> Self {
+        Cone {
+            placement: Placement::new(base, &axis),
+            radius,
+            height: axis.length(),
+            capped,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let local = self.placement.to_local(ray);
+        let (o, d) = (local.origin, local.direction);
+        let mut closest: Option<LocalHit> = None;
+
+        // x^2 + y^2 = k^2 (h - z)^2, where k is the slope of the side.
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let w = self.height - o.z;
+
+        let a = d.x * d.x + d.y * d.y - k2 * d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.y * d.y + k2 * w * d.z);
+        let c = o.x * o.x + o.y * o.y - k2 * w * w;
+
+        for t in quadric_roots(a, b, c) {
+            let p = local.at(t);
+            if (0.0..=self.height).contains(&p.z) {
+                let normal = Vec3::new(p.x, p.y, k2 * (self.height - p.z));
+
+                // Up the side, narrowing by `k` as it goes.
+                let (out, _) = radial(&p);
+                let dpdv = self.height * (Vec3::new(0.0, 0.0, 1.0) - k * out);
+                keep_closest(&mut closest, LocalHit { t, normal, u: azimuth_u(&p), v: p.z / self.height, dpdu: azimuth_dpdu(&p), dpdv }, t_min, t_max);
+            }
+        }
+
+        if self.capped {
+            if let Some(cap) = hit_cap(&local, 0.0, self.radius, -1.0) {
+                keep_closest(&mut closest, cap, t_min, t_max);
+            }
+        }
+
+        match closest {
+            Some(hit) => { self.placement.record(ray, &hit, self.material.clone(), rec); true }
+            None => false,
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        let r = self.radius;
+        Some(self.placement.bounding_box(Point3::new(-r, -r, 0.0), Point3::new(r, r, self.height)))
+    }
+}
+
+
+/// A paraboloid bowl with its vertex at `base`, opening along `axis` and
+/// reaching `radius` at `base + axis`. Capping closes the open end.
+pub struct Paraboloid {
+    placement: Placement,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    material: Material,
+}
+
+impl Paraboloid {
+    pub fn new(base: Point3, axis: Vec3, radius: f64, capped: bool, material: Material) -> Self {
+        Paraboloid {
+            placement: Placement::new(base, &axis),
+            radius,
+            height: axis.length(),
+            capped,
+            material,
+        }
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let local = self.placement.to_local(ray);
+        let (o, d) = (local.origin, local.direction);
+        let mut closest: Option<LocalHit> = None;
+
+        // x^2 + y^2 = k z
+        let k = self.radius * self.radius / self.height;
+
+        let a = d.x * d.x + d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.y * d.y) - k * d.z;
+        let c = o.x * o.x + o.y * o.y - k * o.z;
+
+        for t in quadric_roots(a, b, c) {
+            let p = local.at(t);
+            if (0.0..=self.height).contains(&p.z) {
+                let normal = Vec3::new(p.x, p.y, -0.5 * k);
+
+                // Up the side, widening as `rho = sqrt(k z)`; nothing at the vertex.
+                let (out, rho) = radial(&p);
+                let dpdv = match rho < 1e-12 {
+                    true => Vec3::default(),
+                    false => self.height * (Vec3::new(0.0, 0.0, 1.0) + k / (2.0 * rho) * out),
+                };
+                keep_closest(&mut closest, LocalHit { t, normal, u: azimuth_u(&p), v: p.z / self.height, dpdu: azimuth_dpdu(&p), dpdv }, t_min, t_max);
+            }
+        }
+
+        if self.capped {
+            if let Some(cap) = hit_cap(&local, self.height, self.radius, 1.0) {
+                keep_closest(&mut closest, cap, t_min, t_max);
+            }
+        }
+
+        match closest {
+            Some(hit) => { self.placement.record(ray, &hit, self.material.clone(), rec); true }
+            None => false,
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        let r = self.radius;
+        Some(self.placement.bounding_box(Point3::new(-r, -r, 0.0), Point3::new(r, r, self.height)))
+    }
+}
+
+
+/// A ring around `axis` through `center`. `major_radius` is the distance from
+/// the center to the middle of the tube, `minor_radius` the tube's radius.
+pub struct Torus {
+    placement: Placement,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Material,
+}
+
+impl Torus {
+    pub fn new(center: Point3, axis: Vec3, major_radius: f64, minor_radius: f64, material: Material) -> Self {
+        Torus {
+            placement: Placement::new(center, &axis),
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let local = self.placement.to_local(ray);
+
+        // The quartic is badly conditioned for far away origins and long
+        // directions, so solve for a unit direction starting at the
+        // bounding sphere, and map the roots back afterwards.
+        let direction_length = local.direction.length();
+        let d = local.direction / direction_length;
+        let bound = self.major_radius + self.minor_radius;
+
+        let od = local.origin.dot(&d);
+        let bound_discriminant = od * od - (local.origin.length_squared() - bound * bound);
+        if bound_discriminant < 0.0 { return false; }
+        let shift = (-od - bound_discriminant.sqrt()).max(0.0);
+        let o = local.origin + shift * d;
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2)
+        let r2 = self.minor_radius * self.minor_radius;
+        let big_r2 = self.major_radius * self.major_radius;
+        let e = 2.0 * o.dot(&d);
+        let f = o.length_squared() + big_r2 - r2;
+
+        let a4 = 1.0;
+        let a3 = 2.0 * e;
+        let a2 = e * e + 2.0 * f - 4.0 * big_r2 * (d.x * d.x + d.y * d.y);
+        let a1 = 2.0 * e * f - 8.0 * big_r2 * (o.x * d.x + o.y * d.y);
+        let a0 = f * f - 4.0 * big_r2 * (o.x * o.x + o.y * o.y);
+
+        let mut closest: Option<LocalHit> = None;
+        for s in solve_quartic(a4, a3, a2, a1, a0) {
+            let t = (s + shift) / direction_length;
+            let p = o + s * d;
+
+            let sum = p.length_squared();
+            let normal = Vec3::new(p.x * (sum - big_r2 - r2), p.y * (sum - big_r2 - r2), p.z * (sum + big_r2 - r2));
+
+            let (out, rho) = radial(&p);
+            let u = azimuth_u(&p);
+            let v = (p.z.atan2(rho - self.major_radius) / (2.0 * PI)).rem_euclid(1.0);
+
+            // Around the tube.
+            let dpdv = 2.0 * PI * (Vec3::new(0.0, 0.0, rho - self.major_radius) - p.z * out);
+
+            keep_closest(&mut closest, LocalHit { t, normal, u, v, dpdu: azimuth_dpdu(&p), dpdv }, t_min, t_max);
+        }
+
+        match closest {
+            Some(hit) => { self.placement.record(ray, &hit, self.material.clone(), rec); true }
+            None => false,
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        let outer = self.major_radius + self.minor_radius;
+        let r = self.minor_radius;
+        Some(self.placement.bounding_box(Point3::new(-outer, -outer, -r), Point3::new(outer, outer, r)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialType;
+    use crate::plane::Disk;
+    use crate::vector::Color;
+
+    fn hit(object: &dyn Hittable, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        let mut rec = HitRecord::default();
+        match object.hit(&Ray::new(origin, direction), 0.001, f64::INFINITY, &mut rec) {
+            true => Some(rec),
+            false => None,
+        }
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-6, "{} != {}", a, b);
+    }
+
+    /// Step a little way along each tangent at the hit, and check the
+    /// surface there has moved on in `u` or `v` by as much.
+    fn assert_tangents(object: &dyn Hittable, origin: Point3, direction: Vec3) {
+        let rec = hit(object, origin, direction).unwrap();
+        let h = 1e-4;
+        let wrapped = |d: f64| (d + 0.5).rem_euclid(1.0) - 0.5;
+
+        for (dp, du, dv) in [(rec.dpdu, h, 0.0), (rec.dpdv, 0.0, h)] {
+            assert!(dp.dot(&rec.normal).abs() < 1e-6 * dp.length(), "{} isn't tangent", dp);
+
+            let moved = rec.p + h * dp;
+            let there = hit(object, moved + 0.01 * rec.normal, -rec.normal).unwrap();
+            assert!((wrapped(there.u - rec.u) - du).abs() < 1e-6, "u: {} vs {}", there.u - rec.u, du);
+            assert!((wrapped(there.v - rec.v) - dv).abs() < 1e-6, "v: {} vs {}", there.v - rec.v, dv);
+        }
+    }
+
+    #[test]
+    fn test_hit_quadrics() {
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+
+        // Down the axis onto the top cap, or right through an open tube.
+        let can = Cylinder::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), 1.0, true, material.clone());
+        let tube = Cylinder::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), 1.0, false, material.clone());
+        let rec = hit(&can, Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-9 && rec.front_face);
+        assert_close(rec.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(hit(&tube, Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).is_none());
+
+        // Onto the side, square on and at a slant, with the normal straight out.
+        let rec = hit(&can, Point3::new(5.0, 1.0, 0.0), Vec3::new(-1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9 && rec.front_face);
+        assert_close(rec.normal, Vec3::new(1.0, 0.0, 0.0));
+        let rec = hit(&tube, Point3::new(0.0, 0.5, 5.0), Vec3::new(0.0, 0.1, -1.0)).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9 && rec.front_face);
+        assert_close(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+
+        // From inside the open tube, out through the far wall.
+        let rec = hit(&tube, Point3::new(0.0, 1.0, 0.0), Vec3::new(0.6, 0.0, 0.8)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-9 && !rec.front_face);
+        assert_close(rec.normal, Vec3::new(-0.6, 0.0, -0.8));
+
+        // Up the axis into the cone's base cap, and across onto its side
+        // half way up, where it's half as wide.
+        let cone = Cone::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), 1.0, true, material.clone());
+        let rec = hit(&cone, Point3::new(0.0, -3.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-9 && rec.front_face);
+        assert_close(rec.normal, Vec3::new(0.0, -1.0, 0.0));
+
+        let rec = hit(&cone, Point3::new(5.0, 1.0, 0.0), Vec3::new(-1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-9 && rec.front_face);
+        assert_close(rec.normal, Vec3::new(2.0, 1.0, 0.0).unit_vector());
+
+        // Down the axis, in through the open top to the inside of the vertex.
+        let bowl = Paraboloid::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), 1.0, false, material.clone());
+        let rec = hit(&bowl, Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-9 && !rec.front_face);
+        assert_close(rec.normal, Vec3::new(0.0, 1.0, 0.0));
+
+        // Down the axis through the hole, and across into the outside of the ring.
+        let ring = Torus::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 2.0, 0.5, material);
+        assert!(hit(&ring, Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).is_none());
+        let rec = hit(&ring, Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 2.5).abs() < 1e-6 && rec.front_face);
+        assert_close(rec.normal, Vec3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_tangents_match_uv() {
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+        let (base, axis) = (Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0));
+        let across = Vec3::new(-1.0, 0.0, 0.0);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+
+        let cylinder = Cylinder::new(base, axis, 1.0, true, material.clone());
+        assert_tangents(&cylinder, Point3::new(5.0, 1.2, 0.3), across);
+        assert_tangents(&cylinder, Point3::new(0.3, 5.0, 0.4), down);
+
+        let cone = Cone::new(base, axis, 1.0, true, material.clone());
+        assert_tangents(&cone, Point3::new(5.0, 0.7, 0.2), across);
+        assert_tangents(&cone, Point3::new(0.3, -5.0, -0.4), -down);
+
+        let bowl = Paraboloid::new(base, axis, 1.0, true, material.clone());
+        assert_tangents(&bowl, Point3::new(5.0, 1.0, 0.3), across);
+
+        let ring = Torus::new(base, Vec3::new(0.0, 1.0, 0.0), 2.0, 0.5, material.clone());
+        assert_tangents(&ring, Point3::new(-5.0, 0.2, 0.3), -across);
+        assert_tangents(&ring, Point3::new(1.8, 5.0, 0.6), down);
+
+        let disk = Disk::new(base, Vec3::new(0.0, 1.0, 0.0), 1.0, material);
+        assert_tangents(&disk, Point3::new(0.3, 5.0, -0.4), down);
+    }
+}
@@ -0,0 +1,497 @@
+// Where the random numbers for each sample come from. Rather than drawing
+// every one independently, a sampler knows which pixel and which of its
+// samples it's working on, and how many numbers that sample has used so far
+// (its dimension), so the numbers for the same dimension across a pixel's
+// samples can be spread evenly over [0, 1) instead of clumping as
+// independent ones do. Paths use up dimensions in the same order every
+// time: the pixel position, the lens, then each bounce's choices in turn,
+// each bounce starting on a block of its own so that a choice one sample
+// makes on its second bounce lines up with the same choice in the others.
+//
+// Only code that's handed the sampler uses it. Media pick their scattering
+// distances inside `Hittable::hit`, which doesn't get one, so those stay
+// independent.
+
+use std::sync::OnceLock;
+
+use crate::utility::random_f64;
+
+
+/// How a `Sampler` spreads out its numbers.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SamplerKind {
+    /// Every number independently uniform, as plain `random_f64`.
+    Independent,
+
+    /// Jittered: each dimension split into one stratum per sample (a grid
+    /// of them for pairs), with the samples in a random order and a random
+    /// place within their strata.
+    Stratified,
+
+    /// The first two dimensions of the Sobol sequence, Owen scrambled,
+    /// with each dimension or pair of dimensions shuffled differently so
+    /// that they don't correlate, after Burley's "Practical Hash-based Owen
+    /// Scrambling" (2020). Best with a power of two samples per pixel.
+    Sobol,
+
+    /// The Halton sequence, a prime base per dimension, Owen scrambled per
+    /// pixel. Dimensions past the last prime in `PRIMES` are independent.
+    Halton,
+
+    /// Sobol as above, but the same for every pixel and shifted by a blue
+    /// noise mask rather than scrambled per pixel, so what error is left
+    /// is spread across the image as fine grain rather than blotches.
+    BlueNoise,
+}
+
+impl SamplerKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "independent" => Some(SamplerKind::Independent),
+            "stratified" => Some(SamplerKind::Stratified),
+            "sobol" => Some(SamplerKind::Sobol),
+            "halton" => Some(SamplerKind::Halton),
+            "bluenoise" => Some(SamplerKind::BlueNoise),
+            _ => None,
+        }
+    }
+}
+
+
+pub struct Sampler {
+    kind: SamplerKind,
+    samples_per_pixel: u32,
+
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl Sampler {
+    pub fn new(kind: SamplerKind, samples_per_pixel: u32) -> Self {
+        Sampler { kind, samples_per_pixel, pixel: (0, 0), index: 0, dimension: 0 }
+    }
+
+    /// Start on sample `index` of the pixel at `(x, y)`, from its first
+    /// dimension.
+    pub fn start_pixel_sample(self: &mut Self, x: usize, y: usize, index: u32) {
+        self.pixel = (x as u32, y as u32);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    /// How many numbers the sample has used so far.
+    pub fn dimension(self: &Self) -> u32 {
+        self.dimension
+    }
+
+    /// Carry on from `dimension`, so that what comes next gets the same
+    /// dimensions in every sample however many came before. Never goes
+    /// back, though: a sample that's already past it carries on from there
+    /// rather than using any number twice.
+    pub fn start_dimension(self: &mut Self, dimension: u32) {
+        self.dimension = self.dimension.max(dimension);
+    }
+
+    /// The next number of the sample, in `[0, 1)`.
+    pub fn get_1d(self: &mut Self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        let seed = hash(&[self.pixel.0 as u64, self.pixel.1 as u64, dimension as u64]);
+
+        match self.kind {
+            SamplerKind::Independent => random_f64(None, None),
+            SamplerKind::Stratified => {
+                let stratum = permutation_element(self.index, self.samples_per_pixel, seed as u32);
+                (stratum as f64 + random_f64(None, None)) / self.samples_per_pixel as f64
+            }
+            SamplerKind::Sobol => sobol_1d(self.index, seed as u32),
+            SamplerKind::Halton => match PRIMES.get(dimension as usize) {
+                Some(&base) => owen_scrambled_radical_inverse(base, self.index as u64, seed),
+                None => random_f64(None, None),
+            },
+            SamplerKind::BlueNoise => {
+                let shift = blue_noise(self.pixel, dimension);
+                (sobol_1d(self.index, hash(&[dimension as u64]) as u32) + shift).fract()
+            }
+        }
+    }
+
+    /// The next two numbers of the sample, spread out as a pair.
+    pub fn get_2d(self: &mut Self) -> (f64, f64) {
+        let dimension = self.dimension;
+        self.dimension += 2;
+        let seed = hash(&[self.pixel.0 as u64, self.pixel.1 as u64, dimension as u64]);
+
+        match self.kind {
+            SamplerKind::Independent => (random_f64(None, None), random_f64(None, None)),
+            SamplerKind::Stratified => {
+                // As square a grid as covers every sample.
+                let nx = (self.samples_per_pixel as f64).sqrt().ceil() as u32;
+                let ny = self.samples_per_pixel.div_ceil(nx);
+                let cell = permutation_element(self.index, nx * ny, seed as u32);
+                (
+                    ((cell % nx) as f64 + random_f64(None, None)) / nx as f64,
+                    ((cell / nx) as f64 + random_f64(None, None)) / ny as f64,
+                )
+            }
+            SamplerKind::Sobol => sobol_2d(self.index, seed as u32),
+            SamplerKind::Halton => match (PRIMES.get(dimension as usize), PRIMES.get(dimension as usize + 1)) {
+                (Some(&a), Some(&b)) => (
+                    owen_scrambled_radical_inverse(a, self.index as u64, seed),
+                    owen_scrambled_radical_inverse(b, self.index as u64, mix_bits(seed)),
+                ),
+                _ => (random_f64(None, None), random_f64(None, None)),
+            },
+            SamplerKind::BlueNoise => {
+                let (x, y) = sobol_2d(self.index, hash(&[dimension as u64]) as u32);
+                ((x + blue_noise(self.pixel, dimension)).fract(), (y + blue_noise(self.pixel, dimension + 1)).fract())
+            }
+        }
+    }
+}
+
+
+/// Just below 1, for keeping numbers in `[0, 1)`.
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+fn to_unit(bits: u32) -> f64 {
+    (bits as f64 / 4294967296.0).min(ONE_MINUS_EPSILON)
+}
+
+
+/// Sample `index` of the first Sobol dimension (the van der Corput
+/// sequence), shuffled and scrambled by `seed`.
+fn sobol_1d(index: u32, seed: u32) -> f64 {
+    let index = nested_uniform_scramble(index, seed);
+    to_unit(nested_uniform_scramble(index.reverse_bits(), hash_combine(seed, 0)))
+}
+
+/// Sample `index` of the first two Sobol dimensions, shuffled and
+/// scrambled by `seed`.
+fn sobol_2d(index: u32, seed: u32) -> (f64, f64) {
+    let index = nested_uniform_scramble(index, seed);
+    let x = nested_uniform_scramble(index.reverse_bits(), hash_combine(seed, 0));
+    let y = nested_uniform_scramble(sobol_second_dimension(index), hash_combine(seed, 1));
+    (to_unit(x), to_unit(y))
+}
+
+/// The second Sobol dimension, whose generator matrix is Pascal's triangle
+/// mod 2.
+fn sobol_second_dimension(index: u32) -> u32 {
+    let mut direction: u32 = 1 << 31;
+    let mut x = 0;
+    for bit in 0..32 {
+        if (index >> bit) & 1 == 1 {
+            x ^= direction;
+        }
+        direction ^= direction >> 1;
+    }
+    x
+}
+
+/// Owen scrambling: a random permutation of each digit of `x`, chosen by
+/// the digits above it, so that strata stay strata.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// A hash in which each bit depends only on the bits below it.
+fn laine_karras_permutation(x: u32, seed: u32) -> u32 {
+    let mut x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+fn hash_combine(seed: u32, v: u32) -> u32 {
+    seed ^ (v.wrapping_add(seed << 6).wrapping_add(seed >> 2))
+}
+
+
+/// Bases for the Halton sequence's dimensions.
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+    137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199, 211, 223,
+    227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307, 311,
+];
+
+/// The digits of `a` in `base`, mirrored about the point, each permuted at
+/// random depending on the digits before it.
+fn owen_scrambled_radical_inverse(base: u64, a: u64, seed: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut inverse_base_m = 1.0;
+    let mut value = 0.0;
+    let mut a = a;
+
+    // The digits so far, which pick the permutation for the next. Only
+    // hashed, so it can wrap: with larger bases it would overflow long
+    // before the digits stop mattering.
+    let mut reversed: u64 = 0;
+
+    // Until the digits get too small to matter.
+    while 1.0 - (base - 1) as f64 * inverse_base_m < 1.0 {
+        let next = a / base;
+        let digit = permutation_element((a - next * base) as u32, base as u32, mix_bits(seed ^ reversed) as u32);
+        reversed = reversed.wrapping_mul(base).wrapping_add(digit as u64);
+        inverse_base_m *= inverse_base;
+        value += digit as f64 * inverse_base_m;
+        a = next;
+    }
+
+    value.min(ONE_MINUS_EPSILON)
+}
+
+
+/// Element `i` of a random permutation of `0..n` chosen by `seed`, without
+/// building the permutation. After Kensler's "Correlated Multi-Jittered
+/// Sampling" (2013).
+fn permutation_element(i: u32, n: u32, seed: u32) -> u32 {
+    if n <= 1 {
+        return 0;
+    }
+
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    let p = seed;
+    let mut i = i;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            break;
+        }
+    }
+    i.wrapping_add(p) % n
+}
+
+
+fn mix_bits(v: u64) -> u64 {
+    let mut v = v;
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5d329728ea185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81dadef4bc2dd44d);
+    v ^= v >> 33;
+    v
+}
+
+fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |h, v| mix_bits(h ^ mix_bits(*v)))
+}
+
+
+/// Pixels per side of the blue noise mask, which tiles the image.
+const MASK_SIZE: usize = 64;
+
+/// The blue noise mask's value at `pixel`, for `dimension`. Each dimension
+/// uses the mask shifted by a different amount, so they don't line up.
+fn blue_noise(pixel: (u32, u32), dimension: u32) -> f64 {
+    let mask = BLUE_NOISE.get_or_init(void_and_cluster);
+    let shift = mix_bits(hash(&[dimension as u64]));
+    let x = (pixel.0 as usize + (shift as usize % MASK_SIZE)) % MASK_SIZE;
+    let y = (pixel.1 as usize + ((shift >> 32) as usize % MASK_SIZE)) % MASK_SIZE;
+    mask[y * MASK_SIZE + x]
+}
+
+static BLUE_NOISE: OnceLock<Vec<f64>> = OnceLock::new();
+
+/// A blue noise mask by Ulichney's void-and-cluster method: every pixel
+/// gets a rank, in an order that keeps each prefix of pixels as evenly
+/// spread as can be, and its value is its rank over the number of pixels.
+fn void_and_cluster() -> Vec<f64> {
+    let n = MASK_SIZE * MASK_SIZE;
+
+    // How crowded each pixel is by those already chosen, through a
+    // Gaussian wrapped around the tile.
+    let sigma: f64 = 1.5;
+    let mut kernel = vec![0.0; n];
+    for dy in 0..MASK_SIZE {
+        for dx in 0..MASK_SIZE {
+            let wrap = |d: usize| d.min(MASK_SIZE - d) as f64;
+            kernel[dy * MASK_SIZE + dx] = (-(wrap(dx).powi(2) + wrap(dy).powi(2)) / (2.0 * sigma * sigma)).exp();
+        }
+    }
+    let splat = |energy: &mut [f64], pixel: usize, sign: f64| {
+        let (px, py) = (pixel % MASK_SIZE, pixel / MASK_SIZE);
+        for y in 0..MASK_SIZE {
+            for x in 0..MASK_SIZE {
+                let (dx, dy) = ((x + MASK_SIZE - px) % MASK_SIZE, (y + MASK_SIZE - py) % MASK_SIZE);
+                energy[y * MASK_SIZE + x] += sign * kernel[dy * MASK_SIZE + dx];
+            }
+        }
+    };
+    let tightest_cluster = |chosen: &[bool], energy: &[f64]| (0..n).filter(|&p| chosen[p]).max_by(|&a, &b| energy[a].total_cmp(&energy[b])).unwrap();
+    let largest_void = |chosen: &[bool], energy: &[f64]| (0..n).filter(|&p| !chosen[p]).min_by(|&a, &b| energy[a].total_cmp(&energy[b])).unwrap();
+
+    // A tenth of the pixels to start with, picked at random and then
+    // moved from clusters into voids until that stops helping.
+    let initial = n / 10;
+    let mut chosen = vec![false; n];
+    let mut energy = vec![0.0; n];
+    let mut count = 0;
+    let mut i = 0;
+    while count < initial {
+        let pixel = (hash(&[i]) % n as u64) as usize;
+        i += 1;
+        if !chosen[pixel] {
+            chosen[pixel] = true;
+            splat(&mut energy, pixel, 1.0);
+            count += 1;
+        }
+    }
+    for _ in 0..n {
+        let cluster = tightest_cluster(&chosen, &energy);
+        chosen[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+
+        let void = largest_void(&chosen, &energy);
+        chosen[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; n];
+
+    // The starting pixels rank below the rest, most clustered last.
+    let (mut shrinking, mut shrinking_energy) = (chosen.clone(), energy.clone());
+    for r in (0..initial).rev() {
+        let cluster = tightest_cluster(&shrinking, &shrinking_energy);
+        shrinking[cluster] = false;
+        splat(&mut shrinking_energy, cluster, -1.0);
+        rank[cluster] = r;
+    }
+
+    // The rest fill in the largest voids in turn.
+    for r in initial..n {
+        let void = largest_void(&chosen, &energy);
+        chosen[void] = true;
+        splat(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+
+    rank.iter().map(|&r| (r as f64 + 0.5) / n as f64).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether `values` has exactly one in each of `n` equal bins.
+    fn one_per_bin(values: impl Iterator<Item = f64>, n: usize) -> bool {
+        let mut bins = vec![0; n];
+        for v in values {
+            bins[(v * n as f64) as usize] += 1;
+        }
+        bins.iter().all(|&b| b == 1)
+    }
+
+    #[test]
+    fn test_permutation_element_is_a_permutation() {
+        for n in [1, 5, 64, 100] {
+            let mut seen = vec![false; n as usize];
+            for i in 0..n {
+                seen[permutation_element(i, n, 12345) as usize] = true;
+            }
+            assert!(seen.iter().all(|&s| s), "not a permutation of 0..{}", n);
+        }
+    }
+
+    #[test]
+    fn test_start_dimension_lines_samples_up() {
+        // However many numbers each sample used before, those after line up.
+        let n = 64;
+        let mut sampler = Sampler::new(SamplerKind::Sobol, n);
+        let samples: Vec<f64> = (0..n)
+            .map(|i| {
+                sampler.start_pixel_sample(3, 7, i);
+                for _ in 0..i % 5 {
+                    sampler.get_1d();
+                }
+                sampler.start_dimension(8);
+                sampler.get_1d()
+            })
+            .collect();
+        assert!(one_per_bin(samples.into_iter(), n as usize));
+
+        // But never go back over numbers already used.
+        sampler.start_pixel_sample(3, 7, 0);
+        sampler.get_2d();
+        sampler.start_dimension(1);
+        assert_eq!(sampler.dimension(), 2);
+    }
+
+    #[test]
+    fn test_samplers_are_stratified() {
+        let n = 64;
+        for kind in [SamplerKind::Stratified, SamplerKind::Sobol] {
+            let mut sampler = Sampler::new(kind, n);
+            let samples: Vec<(f64, f64, f64)> = (0..n)
+                .map(|i| {
+                    sampler.start_pixel_sample(3, 7, i);
+                    let x = sampler.get_1d();
+                    let (u, v) = sampler.get_2d();
+                    (x, u, v)
+                })
+                .collect();
+
+            assert!(one_per_bin(samples.iter().map(|s| s.0), n as usize), "{:?} 1D", kind);
+            let cell = |s: &(f64, f64, f64)| ((s.1 * 8.0).floor() * 8.0 + (s.2 * 8.0).floor() + 0.5) / 64.0;
+            assert!(one_per_bin(samples.iter().map(cell), n as usize), "{:?} 2D", kind);
+        }
+
+        // Halton is stratified in powers of each base instead.
+        let mut sampler = Sampler::new(SamplerKind::Halton, 27);
+        let samples: Vec<(f64, f64)> = (0..27)
+            .map(|i| {
+                sampler.start_pixel_sample(3, 7, i);
+                sampler.get_2d()
+            })
+            .collect();
+        assert!(one_per_bin(samples.iter().map(|s| s.1), 27));
+
+        // Right up to the last prime, whose digits would overflow a u64.
+        let mut sampler = Sampler::new(SamplerKind::Halton, 311);
+        let samples: Vec<f64> = (0..311)
+            .map(|i| {
+                sampler.start_pixel_sample(3, 7, i);
+                sampler.start_dimension(PRIMES.len() as u32 - 1);
+                sampler.get_1d()
+            })
+            .collect();
+        assert!(one_per_bin(samples.into_iter(), 311));
+    }
+
+    #[test]
+    fn test_blue_noise_mask_ranks_every_pixel_once() {
+        let mask = BLUE_NOISE.get_or_init(void_and_cluster);
+        assert!(one_per_bin(mask.iter().copied(), MASK_SIZE * MASK_SIZE));
+    }
+}
@@ -0,0 +1,859 @@
+use std::f64::consts::PI;
+
+use crate::camera::Camera;
+use crate::environment::Environment;
+use crate::hittable::Hittable;
+use crate::csg::Csg;
+use crate::light::Light;
+use crate::material::{Coat, Ior, Material, MaterialType};
+use crate::medium::{ConstantMedium, GridMedium};
+use crate::microfacet;
+use crate::phase::PhaseFunction;
+use crate::plane::{Disk, Plane};
+use crate::principled::Principled;
+use crate::quad::{Cuboid, Quad};
+use crate::quadric::{Cone, Cylinder, Paraboloid, Torus};
+use crate::aabb::Aabb;
+use crate::sdf::{self, SdfObject};
+use crate::heightfield::Heightfield;
+use crate::image_reader::FloatImage;
+use crate::sky::PhysicalSky;
+use crate::sphere::Sphere;
+use crate::texture::Texture;
+use crate::thin_film::ThinFilm;
+use crate::voxel::VoxelGrid;
+use crate::utility::random_f64;
+use crate::vector::{Color, Point3, Vec3};
+use crate::world::World;
+
+
+/// Everything needed to render an image.
+pub struct Scene<T> where T: Hittable {
+    pub world: T,
+
+    /// Punctual lights, sampled at every non-specular hit.
+    pub lights: Vec<Light>,
+
+    /// Emissive objects to aim light samples at. These should also be in
+    /// `world`, which is what rays actually hit.
+    pub area_lights: Vec<Box<dyn Hittable + Send + Sync>>,
+
+    /// What rays that escape `world` see.
+    pub environment: Environment,
+
+    pub camera: Camera,
+}
+
+
+/// Lots of small spheres around three big ones.
+pub fn random_spheres(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = random_f64(None, None);
+            let center: Point3 = Point3::new(a as f64 + 0.9 * random_f64(None, None), 0.2, b as f64 + 0.9 * random_f64(None, None));
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                let sphere_material: Material;
+
+                if choose_mat < 0.5 {
+                    let albedo  = Color::random() * Color::random();
+                    sphere_material = Material::new(MaterialType::Lambertian(albedo));
+                    world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
+                } else if choose_mat < 0.8 {
+                    let albedo = Color::random();
+                    let fuzz = random_f64(Some(0.0), Some(0.5));
+                    sphere_material = Material::new(MaterialType::Metal(albedo, fuzz));
+                    world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
+                } else {
+                    sphere_material = Material::new(MaterialType::Dielectric(Ior::Constant(1.5), Color::default()));
+                    world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
+                }
+            }
+        }
+    }
+
+    let material1 = Material::new(MaterialType::Dielectric(Ior::Constant(1.5), Color::default()));
+    world.add(Box::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, material1)));
+
+    let material2 = Material::new(MaterialType::Lambertian(Color::new(0.4, 0.2, 0.1)));
+    world.add(Box::new(Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, material2)));
+
+    let material3 = Material::new(MaterialType::Metal(Color::new(0.7, 0.6, 0.5), 0.0));
+    world.add(Box::new(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, material3)));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::default(),
+        camera: default_camera(aspect_ratio),
+    }
+}
+
+/// A dimly lit row of spheres under a point, a spot and a directional light.
+pub fn punctual_lights(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    world.add(Box::new(Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, Material::new(MaterialType::Lambertian(Color::new(0.8, 0.3, 0.3))))));
+    world.add(Box::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, Material::new(MaterialType::Lambertian(Color::new(0.3, 0.8, 0.3))))));
+    world.add(Box::new(Disk::new(Point3::new(0.0, 0.001, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.8, Material::new(MaterialType::Lambertian(Color::new(0.8, 0.8, 0.8))))));
+    world.add(Box::new(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, Material::new(MaterialType::Metal(Color::new(0.7, 0.6, 0.5), 0.1)))));
+
+    let lights = vec![
+        Light::Point(Point3::new(-4.0, 3.5, 2.0), Color::new(12.0, 10.0, 8.0)),
+        Light::spot(Point3::new(0.0, 6.0, 2.0), Point3::new(0.0, 0.0, 0.0), Color::new(40.0, 40.0, 60.0), 15.0, 25.0),
+        Light::directional(Vec3::new(-1.0, -2.0, -1.0), Color::new(0.6, 0.5, 0.4)),
+    ];
+
+    Scene {
+        world,
+        lights,
+        area_lights: vec![],
+        environment: Environment::Constant(Color::new(0.02, 0.02, 0.03)),
+        camera: default_camera(aspect_ratio),
+    }
+}
+
+/// A pipe, a cone, a bowl and a ring on a plane.
+pub fn quadrics(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let copper = Material::new(MaterialType::Metal(Color::new(0.8, 0.5, 0.3), 0.2));
+    let red = Material::new(MaterialType::Lambertian(Color::new(0.7, 0.2, 0.2)));
+    let blue = Material::new(MaterialType::Lambertian(Color::new(0.2, 0.3, 0.7)));
+    let gold = Material::new(MaterialType::Metal(Color::new(0.9, 0.7, 0.3), 0.05));
+
+    world.add(Box::new(Cylinder::new(Point3::new(0.0, 0.5, -3.0), Vec3::new(0.0, 0.0, 3.0), 0.5, false, copper)));
+    world.add(Box::new(Cone::new(Point3::new(-0.5, 0.0, 2.0), Vec3::new(0.0, 2.0, 0.0), 0.8, true, red)));
+    world.add(Box::new(Paraboloid::new(Point3::new(2.5, 0.0, -0.5), Vec3::new(0.0, 1.5, 0.0), 0.9, false, blue)));
+    world.add(Box::new(Torus::new(Point3::new(5.0, 0.3, 1.5), Vec3::new(0.0, 1.0, 0.0), 0.9, 0.3, gold)));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::default(),
+        camera: default_camera(aspect_ratio),
+    }
+}
+
+/// A glass lens, a sphere with a corner cut away and a cross made of pipes.
+pub fn csg(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let glass = Material::new(MaterialType::Dielectric(Ior::Constant(1.5), Color::default()));
+    let lens = Csg::intersection(
+        Sphere::new(Point3::new(0.0, 1.2, -1.6), 2.0, glass.clone()),
+        Sphere::new(Point3::new(0.0, 1.2, 1.6), 2.0, glass),
+    );
+    world.add(Box::new(lens));
+
+    let orange = Material::new(MaterialType::Lambertian(Color::new(0.8, 0.4, 0.1)));
+    let white = Material::new(MaterialType::Lambertian(Color::new(0.8, 0.8, 0.8)));
+    let carved = Csg::difference(
+        Sphere::new(Point3::new(-3.5, 1.0, 0.0), 1.0, orange),
+        Cuboid::new(Point3::new(-3.5, 1.0, 0.0), Point3::new(-2.0, 2.5, 1.5), white),
+    );
+    world.add(Box::new(carved));
+
+    let steel = Material::new(MaterialType::Metal(Color::new(0.7, 0.7, 0.75), 0.1));
+    let cross = Csg::union(
+        Cylinder::new(Point3::new(3.5, 1.0, -1.2), Vec3::new(0.0, 0.0, 2.4), 0.3, true, steel.clone()),
+        Cylinder::new(Point3::new(3.5, 0.0, 0.0), Vec3::new(0.0, 2.2, 0.0), 0.3, true, steel),
+    );
+    world.add(Box::new(cross));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::default(),
+        camera: Camera::new(Point3::new(0.0, 3.0, 12.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 30.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Procedural shapes from signed distance fields: a Mandelbulb, a blobby
+/// smooth union, a rounded box with a torus cut out of it and a die.
+pub fn sdf_shapes(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let gold = Material::new(MaterialType::Metal(Color::new(0.8, 0.6, 0.2), 0.2));
+    let center = Point3::new(0.0, 1.2, 0.0);
+    let bulb = sdf::mandelbulb(center, 1.0, 12);
+    let bounds = Aabb::new(center - Vec3::new(1.3, 1.3, 1.3), center + Vec3::new(1.3, 1.3, 1.3));
+    world.add(Box::new(SdfObject::new(bulb, bounds, 1e-4, 256, gold)));
+
+    let blue = Material::new(MaterialType::Lambertian(Color::new(0.2, 0.3, 0.8)));
+    let blob = sdf::smooth_union(
+        sdf::sphere(Point3::new(-3.8, 0.8, 0.0), 0.8),
+        sdf::capsule(Point3::new(-3.0, 0.4, 0.0), Point3::new(-2.4, 1.6, 0.0), 0.35),
+        0.4,
+    );
+    let bounds = Aabb::new(Point3::new(-4.8, -0.1, -1.0), Point3::new(-1.9, 2.1, 1.0));
+    world.add(Box::new(SdfObject::new(blob, bounds, 1e-4, 128, blue)));
+
+    let red = Material::new(MaterialType::Lambertian(Color::new(0.8, 0.2, 0.2)));
+    let block = sdf::smooth_difference(
+        sdf::round_box(Point3::new(3.2, 0.8, 0.0), Vec3::new(0.7, 0.7, 0.7), 0.1),
+        sdf::torus(Point3::new(3.2, 1.6, 0.0), 0.6, 0.25),
+        0.1,
+    );
+    let bounds = Aabb::new(Point3::new(2.3, -0.1, -0.9), Point3::new(4.1, 1.7, 0.9));
+    world.add(Box::new(SdfObject::new(block, bounds, 1e-4, 128, red)));
+
+    // A die, modelled at the origin and then moved into place.
+    let ivory = Material::new(MaterialType::Lambertian(Color::new(0.9, 0.88, 0.8)));
+    let body = sdf::intersection(
+        sdf::round_box(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.35, 0.35, 0.35), 0.0),
+        sdf::sphere(Point3::new(0.0, 0.0, 0.0), 0.5),
+    );
+    let pips = sdf::union(
+        sdf::sphere(Point3::new(0.0, 0.0, 0.42), 0.12),
+        sdf::union(
+            sdf::sphere(Point3::new(0.42, 0.15, 0.15), 0.1),
+            sdf::sphere(Point3::new(0.42, -0.15, -0.15), 0.1),
+        ),
+    );
+    let die = sdf::translate(sdf::round(sdf::difference(body, pips), 0.05), Vec3::new(1.5, 0.45, 2.5));
+    let bounds = Aabb::new(Point3::new(1.0, -0.05, 2.0), Point3::new(2.0, 0.95, 3.0));
+    world.add(Box::new(SdfObject::new(die, bounds, 1e-4, 128, ivory)));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::default(),
+        camera: Camera::new(Point3::new(0.0, 3.0, 12.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 35.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Rolling hills under a late afternoon sky. Heights come from a greyscale
+/// image if `heightmap` is given, otherwise from a few layers of ripples.
+pub fn terrain(aspect_ratio: f64, heightmap: Option<&str>) -> Scene<World> {
+    let grass = Material::new(MaterialType::Lambertian(Color::new(0.35, 0.45, 0.2)));
+    let corner = Point3::new(-50.0, 0.0, -50.0);
+    let size = Vec3::new(100.0, 12.0, 100.0);
+
+    let field = match heightmap {
+        Some(path) => Heightfield::load(path, corner, size, grass).expect("Could not load heightmap"),
+        None => {
+            let n = 512;
+            let mut heights = Vec::with_capacity(n * n);
+            for j in 0..n {
+                for i in 0..n {
+                    let (x, z) = (i as f64 / n as f64, j as f64 / n as f64);
+                    let mut height = 0.5;
+                    let mut amplitude = 0.25;
+                    let mut frequency = 6.0;
+                    for octave in 0..5 {
+                        let phase = octave as f64 * 1.7;
+                        height += amplitude * (frequency * x + phase).sin() * (frequency * 1.3 * z - phase).cos();
+                        amplitude *= 0.45;
+                        frequency *= 2.1;
+                    }
+                    heights.push(height.clamp(0.0, 1.0));
+                }
+            }
+            Heightfield::new(n, n, heights, corner, size, grass)
+        }
+    };
+
+    let mut world = World::new();
+    world.add(Box::new(field));
+
+    let water = Material::new(MaterialType::Metal(Color::new(0.3, 0.4, 0.5), 0.05));
+    world.add(Box::new(Plane::new(Point3::new(0.0, 3.0, 0.0), Vec3::new(0.0, 1.0, 0.0), water)));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::Sky(PhysicalSky::new(20.0, 60.0, 3.0, 1.0)),
+        camera: Camera::new(Point3::new(0.0, 20.0, 45.0), Point3::new(0.0, 4.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 45.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// A glass of slightly blue water with a rod standing in it, next to a
+/// hollow bubble of green glass. The water slightly overlaps the glass,
+/// which has the higher priority, so the two meet without an air gap.
+pub fn nested_dielectrics(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let glass = Material::new(MaterialType::Dielectric(Ior::Constant(1.5), Color::default())).with_priority(2);
+    let water = Material::new(MaterialType::Dielectric(Ior::Cauchy(1.3199, 0.00653), MaterialType::absorption_for(Color::new(0.7, 0.85, 0.95), 1.0))).with_priority(1);
+
+    let cup = Csg::difference(
+        Cylinder::new(Point3::new(-1.2, 0.0, 0.0), Vec3::new(0.0, 2.5, 0.0), 0.9, true, glass.clone()),
+        Cylinder::new(Point3::new(-1.2, 0.15, 0.0), Vec3::new(0.0, 2.5, 0.0), 0.8, true, glass),
+    );
+    world.add(Box::new(cup));
+    world.add(Box::new(Cylinder::new(Point3::new(-1.2, 0.1, 0.0), Vec3::new(0.0, 1.6, 0.0), 0.85, true, water)));
+
+    let red = Material::new(MaterialType::Lambertian(Color::new(0.8, 0.1, 0.1)));
+    world.add(Box::new(Cylinder::new(Point3::new(-1.6, 0.15, -0.2), Vec3::new(0.9, 2.8, 0.4), 0.06, true, red)));
+
+    // A bubble: the inner sphere's normals face inwards.
+    let bubble_glass = Material::new(MaterialType::Dielectric(Ior::Constant(1.5), MaterialType::absorption_for(Color::new(0.3, 0.8, 0.4), 0.1)));
+    world.add(Box::new(Sphere::new(Point3::new(1.4, 1.0, 0.0), 1.0, bubble_glass.clone())));
+    world.add(Box::new(Sphere::new(Point3::new(1.4, 1.0, 0.0), -0.9, bubble_glass)));
+
+    let blue = Material::new(MaterialType::Lambertian(Color::new(0.1, 0.2, 0.7)));
+    let green = Material::new(MaterialType::Lambertian(Color::new(0.1, 0.6, 0.2)));
+    world.add(Box::new(Cuboid::new(Point3::new(-4.0, 0.0, -4.0), Point3::new(-0.5, 3.0, -3.5), blue)));
+    world.add(Box::new(Cuboid::new(Point3::new(0.5, 0.0, -4.0), Point3::new(4.0, 3.0, -3.5), green)));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::default(),
+        camera: Camera::new(Point3::new(0.0, 2.5, 9.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 30.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// A flint glass prism and a diamond in front of black and white stripes,
+/// which come through fringed with color when rendered with `--spectral`.
+pub fn dispersion(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    // A triangular prism standing on end, one face towards the camera. The
+    // box caps it top and bottom, and keeps it bounded.
+    let flint = Material::new(MaterialType::Dielectric(Ior::SF11, Color::default()));
+    let (x, z) = (-1.3, 0.0);
+    let front = Point3::new(x, 0.0, z + 0.577);
+    let prism = Csg::intersection(
+        Cuboid::new(Point3::new(x - 1.0, 0.0, z - 1.155), Point3::new(x + 1.0, 2.5, z + 0.577), flint.clone()),
+        Csg::intersection(
+            Plane::new(front, Vec3::new(0.0, 0.0, 1.0), flint.clone()),
+            Csg::intersection(
+                Plane::new(front - Vec3::new(1.0, 0.0, 0.0), Vec3::new(-0.866, 0.0, -0.5), flint.clone()),
+                Plane::new(front + Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.866, 0.0, -0.5), flint),
+            ),
+        ),
+    );
+    world.add(Box::new(prism));
+
+    let diamond = Material::new(MaterialType::Dielectric(Ior::DIAMOND, Color::default()));
+    world.add(Box::new(Sphere::new(Point3::new(1.5, 1.0, 0.0), 1.0, diamond)));
+
+    let black = Material::new(MaterialType::Lambertian(Color::new(0.02, 0.02, 0.02)));
+    let white = Material::new(MaterialType::Lambertian(Color::new(0.9, 0.9, 0.9)));
+    for i in 0..16 {
+        let material = if i % 2 == 0 { black.clone() } else { white.clone() };
+        let x = -4.0 + i as f64 * 0.5;
+        world.add(Box::new(Quad::new(Point3::new(x, 0.0, -3.0), Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 4.0, 0.0), material)));
+    }
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::default(),
+        camera: Camera::new(Point3::new(0.0, 1.5, 9.0), Point3::new(0.0, 1.2, 0.0), Vec3::new(0.0, 1.0, 0.0), 30.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Gold, silver, copper and aluminum, from back to front, getting rougher
+/// from left to right.
+pub fn metals(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.4, 0.4, 0.4)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let tables = [microfacet::GOLD, microfacet::SILVER, microfacet::COPPER, microfacet::ALUMINUM];
+    for (row, table) in tables.iter().enumerate() {
+        for (column, roughness) in [0.0, 0.15, 0.35, 0.6].iter().enumerate() {
+            let material = Material::new(MaterialType::conductor(table, *roughness));
+            let center = Point3::new(-3.3 + 2.2 * column as f64, 0.9, -4.5 + 2.2 * row as f64);
+            world.add(Box::new(Sphere::new(center, 0.9, material)));
+        }
+    }
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::Sky(PhysicalSky::new(35.0, 120.0, 3.0, 0.4)),
+        camera: Camera::new(Point3::new(0.0, 7.0, 11.0), Point3::new(0.0, 0.5, -1.0), Vec3::new(0.0, 1.0, 0.0), 35.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Glass spheres getting more frosted from left to right, in front of
+/// colored stripes and under a soft light.
+pub fn frosted_glass(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    for (i, roughness) in [0.0, 0.1, 0.25, 0.5].iter().enumerate() {
+        let glass = Material::new(MaterialType::RoughDielectric(Ior::Constant(1.5), Color::default(), *roughness));
+        world.add(Box::new(Sphere::new(Point3::new(-3.3 + 2.2 * i as f64, 1.0, 0.0), 1.0, glass)));
+    }
+
+    let colors = [Color::new(0.8, 0.1, 0.1), Color::new(0.9, 0.9, 0.9), Color::new(0.1, 0.2, 0.8), Color::new(0.02, 0.02, 0.02)];
+    for i in 0..24 {
+        let material = Material::new(MaterialType::Lambertian(colors[i % colors.len()]));
+        let x = -6.0 + i as f64 * 0.5;
+        world.add(Box::new(Quad::new(Point3::new(x, 0.0, -2.5), Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 4.0, 0.0), material)));
+    }
+
+    let light_material = Material::new(MaterialType::DiffuseLight(Color::new(4.0, 4.0, 4.0)));
+    let light = Quad::new(Point3::new(-3.0, 6.0, -1.0), Vec3::new(6.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 3.0), light_material);
+    world.add(Box::new(light.clone()));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![Box::new(light)],
+        environment: Environment::Constant(Color::new(0.1, 0.1, 0.12)),
+        camera: Camera::new(Point3::new(0.0, 2.0, 10.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 35.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// The principled material's parameters one at a time: plastic getting
+/// rougher at the back, then metal, sheen, clearcoat, glass and subsurface
+/// at the front. Or, given a `.mtl` file, its materials in the front rows.
+pub fn principled(aspect_ratio: f64, mtl: Option<&str>) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.4, 0.4, 0.4)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let base = Principled::new(Color::new(0.8, 0.2, 0.1));
+    for (i, roughness) in [0.0, 0.2, 0.4, 0.6, 0.8, 1.0].iter().enumerate() {
+        let material = Material::new(MaterialType::Principled(Principled { roughness: *roughness, ..base }));
+        world.add(Box::new(Sphere::new(Point3::new(-5.0 + 2.0 * i as f64, 0.9, -2.2), 0.9, material)));
+    }
+
+    let features = match mtl {
+        Some(path) => Principled::load_mtl(path).expect("Could not load materials").into_iter().map(|(_, p)| p).collect(),
+        None => vec![
+            Principled::from_gltf(Color::new(1.0, 0.77, 0.34), 1.0, 0.3),
+            Principled { sheen: 1.0, roughness: 1.0, ..Principled::new(Color::new(0.1, 0.1, 0.4)) },
+            Principled { clearcoat: 1.0, roughness: 0.6, ..base },
+            Principled { transmission: 1.0, roughness: 0.0, ..Principled::new(Color::new(0.9, 1.0, 0.9)) },
+            Principled { transmission: 1.0, roughness: 0.3, ..Principled::new(Color::new(1.0, 1.0, 1.0)) },
+            Principled { subsurface: 1.0, ..Principled::new(Color::new(0.9, 0.8, 0.7)) },
+        ],
+    };
+    // Six to a row, coming towards the camera.
+    for (i, p) in features.iter().enumerate() {
+        let material = Material::new(MaterialType::Principled(*p));
+        let (column, row) = (i % 6, i / 6);
+        world.add(Box::new(Sphere::new(Point3::new(-5.0 + 2.0 * column as f64, 0.9, 0.2 + 2.4 * row as f64), 0.9, material)));
+    }
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::Sky(PhysicalSky::new(35.0, 120.0, 3.0, 0.4)),
+        camera: Camera::new(Point3::new(0.0, 5.0, 11.0), Point3::new(0.0, 0.6, -1.0), Vec3::new(0.0, 1.0, 0.0), 35.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Coated materials on a floor mixing tiles of matte and polished metal:
+/// car paint, bare wood and varnished wood, from left to right.
+pub fn coated(aspect_ratio: f64) -> Scene<World> {
+    let matte = Material::new(MaterialType::Lambertian(Color::new(0.3, 0.3, 0.3)));
+    let polished = Material::new(MaterialType::conductor(microfacet::ALUMINUM, 0.2));
+    let checker = Texture::Checker(Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0), 1.5);
+    let ground_material = Material::new(MaterialType::mix(matte, polished, checker));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let flakes = Material::new(MaterialType::Principled(Principled { metallic: 1.0, roughness: 0.45, ..Principled::new(Color::new(0.6, 0.02, 0.03)) }));
+    let paint = Material::new(MaterialType::layered(Coat::new(1.5, 0.0, Color::default(), 0.0), flakes));
+    world.add(Box::new(Sphere::new(Point3::new(-2.4, 1.0, 0.0), 1.0, paint)));
+
+    let wood = Material::new(MaterialType::Lambertian(Color::new(0.45, 0.25, 0.12)));
+    world.add(Box::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, wood.clone())));
+
+    let amber = MaterialType::absorption_for(Color::new(0.9, 0.7, 0.4), 0.1);
+    let varnished = Material::new(MaterialType::layered(Coat::new(1.5, 0.05, amber, 0.1), wood));
+    world.add(Box::new(Sphere::new(Point3::new(2.4, 1.0, 0.0), 1.0, varnished)));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::Sky(PhysicalSky::new(35.0, 120.0, 3.0, 0.4)),
+        camera: Camera::new(Point3::new(0.0, 3.0, 9.0), Point3::new(0.0, 0.8, 0.0), Vec3::new(0.0, 1.0, 0.0), 35.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Diffuse models side by side, lit from behind the camera with a rim light
+/// from behind: Lambert, then Oren-Nayar getting rougher, then velvet.
+pub fn diffuse(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::OrenNayar(Color::new(0.4, 0.4, 0.4), 0.5));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let clay = Color::new(0.7, 0.4, 0.3);
+    let materials = [
+        MaterialType::Lambertian(clay),
+        MaterialType::OrenNayar(clay, 0.35),
+        MaterialType::OrenNayar(clay, 1.0),
+        MaterialType::Sheen(Color::new(0.15, 0.02, 0.05), Color::new(1.0, 0.6, 0.7), 0.5),
+    ];
+    for (i, material) in materials.into_iter().enumerate() {
+        world.add(Box::new(Sphere::new(Point3::new(-3.3 + 2.2 * i as f64, 1.0, 0.0), 1.0, Material::new(material))));
+    }
+
+    let lights = vec![
+        Light::directional(Vec3::new(0.2, -0.5, -1.0), Color::new(2.0, 2.0, 2.0)),
+        Light::directional(Vec3::new(0.0, -0.3, 1.0), Color::new(1.5, 1.5, 1.5)),
+    ];
+
+    Scene {
+        world,
+        lights,
+        area_lights: vec![],
+        environment: Environment::Constant(Color::new(0.05, 0.05, 0.06)),
+        camera: Camera::new(Point3::new(0.0, 2.0, 9.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 35.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Soap bubbles at the back, their films getting thicker from left to
+/// right, and titanium anodized to different depths at the front.
+pub fn iridescence(aspect_ratio: f64) -> Scene<World> {
+    let checker = Texture::Checker(Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0), 1.0);
+    let ground_material = Material::new(MaterialType::mix(
+        Material::new(MaterialType::Lambertian(Color::new(0.05, 0.05, 0.05))),
+        Material::new(MaterialType::Lambertian(Color::new(0.6, 0.6, 0.6))),
+        checker,
+    ));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    // Air inside and out, with only the soapy water between.
+    let bubble = Material::new(MaterialType::Dielectric(Ior::Constant(1.0), Color::default()));
+    for (i, thickness) in [250.0, 400.0, 550.0, 800.0].iter().enumerate() {
+        let material = bubble.clone().with_film(ThinFilm::new(*thickness, 1.33));
+        world.add(Box::new(Sphere::new(Point3::new(-3.3 + 2.2 * i as f64, 2.0, -1.5), 1.0, material)));
+    }
+
+    // Titanium under its own oxide.
+    let titanium = Material::new(MaterialType::Conductor(Color::new(2.7, 2.5, 2.3), Color::new(3.6, 3.4, 3.1), 0.15));
+    for (i, thickness) in [0.0, 60.0, 110.0, 160.0].iter().enumerate() {
+        let material = titanium.clone().with_film(ThinFilm::new(*thickness, 2.4));
+        world.add(Box::new(Sphere::new(Point3::new(-3.3 + 2.2 * i as f64, 0.8, 1.0), 0.8, material)));
+    }
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::Sky(PhysicalSky::new(15.0, 120.0, 3.0, 0.3)),
+        camera: Camera::new(Point3::new(0.0, 3.0, 10.0), Point3::new(0.0, 1.2, 0.0), Vec3::new(0.0, 1.0, 0.0), 35.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Translucent materials, lit from behind and to the side: skin, marble,
+/// wax and milk, with a plain diffuse sphere for comparison, and a slab of
+/// wax light shines through at the back.
+pub fn subsurface(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.4, 0.4, 0.4)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let materials = [
+        MaterialType::Lambertian(Color::new(0.8, 0.6, 0.5)),
+        MaterialType::Subsurface(Color::new(0.99, 0.9, 0.85), Color::new(0.4, 0.15, 0.08), PhaseFunction::Isotropic),
+        MaterialType::Subsurface(Color::new(0.999, 0.999, 0.998), Color::new(0.25, 0.2, 0.15), PhaseFunction::HenyeyGreenstein(0.3)),
+        MaterialType::Subsurface(Color::new(0.99, 0.9, 0.6), Color::new(0.3, 0.2, 0.1), PhaseFunction::Isotropic),
+        MaterialType::Subsurface(Color::new(0.999, 0.999, 0.99), Color::new(0.1, 0.08, 0.05), PhaseFunction::HenyeyGreenstein(0.7)),
+    ];
+    for (i, material) in materials.iter().enumerate() {
+        world.add(Box::new(Sphere::new(Point3::new(-4.4 + 2.2 * i as f64, 1.0, 0.0), 1.0, Material::new(material.clone()))));
+    }
+
+    let wax = Material::new(materials[3].clone());
+    world.add(Box::new(Cuboid::new(Point3::new(-3.0, 0.0, -3.0), Point3::new(3.0, 2.5, -2.8), wax)));
+
+    let light_material = Material::new(MaterialType::DiffuseLight(Color::new(8.0, 8.0, 8.0)));
+    let light = Quad::new(Point3::new(-6.0, 1.0, -5.0), Vec3::new(0.0, 3.0, 0.0), Vec3::new(0.0, 0.0, 3.0), light_material.clone());
+    world.add(Box::new(light.clone()));
+    let back_light = Quad::new(Point3::new(-2.0, 0.5, -4.0), Vec3::new(4.0, 0.0, 0.0), Vec3::new(0.0, 1.5, 0.0), light_material);
+    world.add(Box::new(back_light.clone()));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![Box::new(light), Box::new(back_light)],
+        environment: Environment::Constant(Color::new(0.05, 0.05, 0.06)),
+        camera: Camera::new(Point3::new(0.0, 3.0, 11.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 35.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Bump mapping: a brick floor from a normal map, and spheres hammered and
+/// rippled by height maps, next to a smooth one for comparison. Lit low
+/// from the side to throw the relief into shadow.
+pub fn bumps(aspect_ratio: f64) -> Scene<World> {
+    // Bricks 1/8 of the floor wide, in rows offset by half a brick, with
+    // mortar sunk between them.
+    let bricks = |u: f64, v: f64| {
+        let row = (v * 16.0).floor();
+        let x = (u * 8.0 + 0.5 * row).fract();
+        let y = (v * 16.0).fract();
+        let edge = x.min(1.0 - x).min(y.min(1.0 - y) * 0.5);
+        (edge / 0.04).min(1.0)
+    };
+    let floor_normals = normal_map(512, 512, 0.01, bricks);
+    let floor_material = Material::new(MaterialType::Lambertian(Color::new(0.55, 0.3, 0.2))).with_normal_map(Texture::image(floor_normals));
+
+    let mut world = World::new();
+    world.add(Box::new(Quad::new(Point3::new(-8.0, 0.0, 6.0), Vec3::new(16.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -16.0), floor_material)));
+
+    let dimples = image(256, 128, |u, v| {
+        let (x, y) = ((u * 48.0).fract() - 0.5, (v * 24.0).fract() - 0.5);
+        Color::new(1.0, 1.0, 1.0) * (x * x + y * y).min(0.25) * 4.0
+    });
+    let ripples = image(256, 128, |_, v| Color::new(1.0, 1.0, 1.0) * (0.5 + 0.5 * (v * 40.0 * PI).sin()));
+
+    let gold = Color::new(1.0, 0.78, 0.34);
+    let spheres = [
+        Material::new(MaterialType::Metal(gold, 0.1)),
+        Material::new(MaterialType::Metal(gold, 0.1)).with_bump_map(Texture::image(dimples), 0.02),
+        Material::new(MaterialType::Lambertian(Color::new(0.2, 0.4, 0.7))).with_bump_map(Texture::image(ripples), 0.01),
+    ];
+    for (i, material) in spheres.into_iter().enumerate() {
+        world.add(Box::new(Sphere::new(Point3::new(-2.4 + 2.4 * i as f64, 1.0, 0.0), 1.0, material)));
+    }
+
+    let lights = vec![Light::directional(Vec3::new(-1.0, -0.4, -0.3), Color::new(3.0, 2.9, 2.7))];
+
+    Scene {
+        world,
+        lights,
+        area_lights: vec![],
+        environment: Environment::Constant(Color::new(0.1, 0.12, 0.15)),
+        camera: Camera::new(Point3::new(0.0, 3.5, 9.0), Point3::new(0.0, 0.8, 0.0), Vec3::new(0.0, 1.0, 0.0), 35.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// Cutouts: a chain-link fence in front of a bush made of leaf cards, each
+/// a quad with a leaf-shaped opacity mask, and a sheet of gauze behind,
+/// half there everywhere, lit by the sun through all three.
+pub fn cutout(aspect_ratio: f64) -> Scene<World> {
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.35, 0.3, 0.25)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    // A leaf pointing along v, narrowing to its tip, with a stalk.
+    let leaf = image(128, 128, |u, v| {
+        let half_width = 0.35 * (PI * v).sin().powf(0.8);
+        let blade = (u - 0.5).abs() < half_width && v > 0.1;
+        let stalk = (u - 0.5).abs() < 0.015 && v <= 0.1;
+        match blade || stalk {
+            true => Color::new(1.0, 1.0, 1.0),
+            false => Color::default(),
+        }
+    });
+    let leaf_alpha = Texture::image(leaf);
+
+    for _ in 0..400 {
+        let green = Color::new(random_f64(Some(0.05), Some(0.15)), random_f64(Some(0.3), Some(0.5)), random_f64(Some(0.02), Some(0.1)));
+        let material = Material::new(MaterialType::Lambertian(green)).with_alpha(leaf_alpha.clone());
+
+        // Stalks on the surface of a squashed ball, leaves pointing out.
+        let out = Vec3::random_unit_vector();
+        let stalk = Point3::new(1.3 * out.x - 1.0, 0.75 + 0.7 * out.y, -1.0 + out.z);
+        let along = (out + 0.6 * Vec3::random_unit_vector()).unit_vector() * 0.5;
+        let across = along.cross(Vec3::random_unit_vector()).unit_vector() * 0.3;
+        world.add(Box::new(Quad::new(stalk - 0.5 * across, across, along, material)));
+    }
+
+    // Wires along both diagonals, 1/24 of the fence apart.
+    let links = image(512, 256, |u, v| {
+        let wire = |x: f64| (x.rem_euclid(1.0) - 0.5).abs() > 0.44;
+        match wire(u * 24.0 + v * 6.0) || wire(u * 24.0 - v * 6.0) {
+            true => Color::new(1.0, 1.0, 1.0),
+            false => Color::default(),
+        }
+    });
+    let fence_material = Material::new(MaterialType::Metal(Color::new(0.6, 0.6, 0.6), 0.4)).with_alpha(Texture::image(links));
+    world.add(Box::new(Quad::new(Point3::new(-1.0, 0.0, 1.0), Vec3::new(6.0, 0.0, 0.0), Vec3::new(0.0, 1.5, 0.0), fence_material)));
+
+    let gauze = Material::new(MaterialType::Lambertian(Color::new(0.9, 0.9, 0.85))).with_alpha(Texture::Constant(Color::new(0.5, 0.5, 0.5)));
+    world.add(Box::new(Quad::new(Point3::new(-4.0, 0.0, -2.5), Vec3::new(8.0, 0.0, 0.0), Vec3::new(0.0, 2.5, 0.0), gauze)));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::Sky(PhysicalSky::new(30.0, 240.0, 3.0, 0.3)),
+        camera: Camera::new(Point3::new(0.0, 3.0, 9.0), Point3::new(0.0, 0.8, 0.0), Vec3::new(0.0, 1.0, 0.0), 40.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// An image of `texel(u, v)` at texel centers, with `v = 0` at the bottom,
+/// as textures read it.
+fn image(width: usize, height: usize, texel: impl Fn(f64, f64) -> Color) -> FloatImage {
+    let mut texels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            texels.push(texel((x as f64 + 0.5) / width as f64, 1.0 - (y as f64 + 0.5) / height as f64));
+        }
+    }
+    FloatImage { width, height, texels }
+}
+
+/// A tangent-space normal map for the heights `heights(u, v)`, times `scale`
+/// texture widths.
+fn normal_map(width: usize, height: usize, scale: f64, heights: impl Fn(f64, f64) -> f64) -> FloatImage {
+    let (du, dv) = (1.0 / width as f64, 1.0 / height as f64);
+    image(width, height, |u, v| {
+        let dhdu = (heights(u + du, v) - heights(u - du, v)) / (2.0 * du) * scale;
+        let dhdv = (heights(u, v + dv) - heights(u, v - dv)) / (2.0 * dv) * scale;
+        let n = Vec3::new(-dhdu, -dhdv, 1.0).unit_vector();
+        0.5 * (n + Vec3::new(1.0, 1.0, 1.0))
+    })
+}
+
+/// A cloud over the ground with a low sun behind it, from a voxel file of the
+/// given size if there is one (sparse `x y z value` lines if it ends in
+/// `.txt`, otherwise raw), or else heaped up out of random blobs.
+pub fn clouds(aspect_ratio: f64, voxels: Option<(&str, [usize; 3])>) -> Scene<World> {
+    let grid = match voxels {
+        Some((path, [nx, ny, nz])) if path.ends_with(".txt") => VoxelGrid::load_sparse(path, nx, ny, nz).expect("Could not load voxels"),
+        Some((path, [nx, ny, nz])) => VoxelGrid::load_raw(path, nx, ny, nz).expect("Could not load voxels"),
+        None => {
+            let (nx, ny, nz) = (96, 48, 64);
+            let blobs: Vec<(Point3, f64)> = (0..24)
+                .map(|_| {
+                    let center = Point3::new(random_f64(Some(0.25), Some(0.75)), random_f64(Some(0.3), Some(0.5)), random_f64(Some(0.3), Some(0.7)));
+                    (center, random_f64(Some(0.1), Some(0.2)))
+                })
+                .collect();
+
+            let mut values = Vec::with_capacity(nx * ny * nz);
+            for z in 0..nz {
+                for y in 0..ny {
+                    for x in 0..nx {
+                        // In cells of the grid's own shape, squashed vertically.
+                        let p = Point3::new((x as f64 + 0.5) / nx as f64, (y as f64 + 0.5) / ny as f64 * 0.5, (z as f64 + 0.5) / nz as f64 * 0.67);
+                        let density: f64 = blobs
+                            .iter()
+                            .map(|(center, radius)| {
+                                let c = Point3::new(center.x, center.y * 0.5, center.z * 0.67);
+                                let falloff = 1.0 - (p - c).length_squared() / (radius * radius);
+                                falloff.max(0.0).powi(2)
+                            })
+                            .sum();
+                        values.push(density.min(1.0) as f32);
+                    }
+                }
+            }
+            VoxelGrid::from_dense(nx, ny, nz, &values)
+        }
+    };
+
+    let ground_material = Material::new(MaterialType::Lambertian(Color::new(0.3, 0.35, 0.25)));
+    let mut world = World::new();
+    world.add(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground_material)));
+
+    let bounds = Aabb::new(Point3::new(-6.0, 1.0, -4.0), Point3::new(6.0, 7.0, 4.0));
+    // Mostly forward scattering, for the silver lining, with a little back.
+    let cloud = GridMedium::new(grid, bounds, 8.0, Color::new(0.98, 0.98, 0.98)).with_phase_function(PhaseFunction::DoubleHenyeyGreenstein(0.8, -0.3, 0.9));
+    world.add(Box::new(cloud));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![],
+        environment: Environment::Sky(PhysicalSky::new(15.0, 260.0, 2.5, 0.5)),
+        camera: Camera::new(Point3::new(0.0, 3.0, 16.0), Point3::new(0.0, 3.5, 0.0), Vec3::new(0.0, 1.0, 0.0), 45.0, aspect_ratio, 0.0, 10.0),
+    }
+}
+
+/// The Cornell box, lit by a single quad in the ceiling.
+pub fn cornell_box(aspect_ratio: f64) -> Scene<World> {
+    let (mut world, light) = cornell_walls();
+
+    let white = Material::new(MaterialType::Lambertian(Color::new(0.73, 0.73, 0.73)));
+    world.add(Box::new(Cuboid::new(Point3::new(130.0, 0.0, 65.0), Point3::new(295.0, 165.0, 230.0), white.clone())));
+    world.add(Box::new(Cuboid::new(Point3::new(265.0, 0.0, 295.0), Point3::new(430.0, 330.0, 460.0), white)));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![Box::new(light)],
+        environment: Environment::Constant(Color::default()),
+        camera: cornell_camera(aspect_ratio),
+    }
+}
+
+/// The Cornell box with its two blocks replaced by smoke and fog, the fog
+/// scattering mostly forward as real droplets do.
+pub fn cornell_smoke(aspect_ratio: f64) -> Scene<World> {
+    let (mut world, light) = cornell_walls();
+
+    let white = Material::new(MaterialType::Lambertian(Color::new(0.73, 0.73, 0.73)));
+    let box1 = Cuboid::new(Point3::new(130.0, 0.0, 65.0), Point3::new(295.0, 165.0, 230.0), white.clone());
+    let box2 = Cuboid::new(Point3::new(265.0, 0.0, 295.0), Point3::new(430.0, 330.0, 460.0), white);
+    world.add(Box::new(ConstantMedium::new(box1, 0.01, Color::new(0.0, 0.0, 0.0))));
+    world.add(Box::new(ConstantMedium::new(box2, 0.01, Color::new(1.0, 1.0, 1.0)).with_phase_function(PhaseFunction::HenyeyGreenstein(0.6))));
+
+    Scene {
+        world,
+        lights: vec![],
+        area_lights: vec![Box::new(light)],
+        environment: Environment::Constant(Color::default()),
+        camera: cornell_camera(aspect_ratio),
+    }
+}
+
+/// The empty box, and its light (which is also in the world).
+fn cornell_walls() -> (World, Quad) {
+    let red = Material::new(MaterialType::Lambertian(Color::new(0.65, 0.05, 0.05)));
+    let white = Material::new(MaterialType::Lambertian(Color::new(0.73, 0.73, 0.73)));
+    let green = Material::new(MaterialType::Lambertian(Color::new(0.12, 0.45, 0.15)));
+    let light_material = Material::new(MaterialType::DiffuseLight(Color::new(15.0, 15.0, 15.0)));
+
+    let light = Quad::new(Point3::new(343.0, 554.0, 332.0), Vec3::new(-130.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -105.0), light_material);
+
+    let mut world = World::new();
+    world.add(Box::new(Quad::new(Point3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Vec3::new(0.0, 0.0, 555.0), green)));
+    world.add(Box::new(Quad::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Vec3::new(0.0, 0.0, 555.0), red)));
+    world.add(Box::new(Quad::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 555.0), white.clone())));
+    world.add(Box::new(Quad::new(Point3::new(555.0, 555.0, 555.0), Vec3::new(-555.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -555.0), white.clone())));
+    world.add(Box::new(Quad::new(Point3::new(0.0, 0.0, 555.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), white)));
+    world.add(Box::new(light.clone()));
+
+    (world, light)
+}
+
+fn cornell_camera(aspect_ratio: f64) -> Camera {
+    let lookfrom = Point3::new(278.0, 278.0, -800.0);
+    let lookat = Point3::new(278.0, 278.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+
+    Camera::new(lookfrom, lookat, vup, 40.0, aspect_ratio, 0.0, 10.0)
+}
+
+fn default_camera(aspect_ratio: f64) -> Camera {
+    let lookfrom = Point3::new(13.0, 2.0, 3.0);
+    let lookat = Point3::new(0.0, 0.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    Camera::new(lookfrom, lookat, vup, 20.0, aspect_ratio, aperture, dist_to_focus)
+}
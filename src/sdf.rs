@@ -0,0 +1,267 @@
+// Signed distance fields, rendered by sphere tracing. Distance functions are
+// plain closures, so they can be built from the primitives and combinators
+// below or written by hand.
+
+use std::f64::consts::PI;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sphere::Sphere;
+use crate::vector::{Point3, Vec3};
+
+
+/// A surface given by the zero set of `distance`, which must never
+/// overestimate the distance to the surface. Only rays passing through
+/// `bounds` are marched.
+pub struct SdfObject<F> where F: Fn(Point3) -> f64 {
+    distance: F,
+    bounds: Aabb,
+
+    /// How close to the surface counts as a hit. Also the finite
+    /// difference step for normals.
+    epsilon: f64,
+
+    /// Give up after this many steps along a ray.
+    max_steps: u32,
+
+    material: Material,
+}
+
+impl<F: Fn(Point3) -> f64> SdfObject<F> {
+    pub fn new(distance: F, bounds: Aabb, epsilon: f64, max_steps: u32, material: Material) -> Self {
+        SdfObject {
+            distance,
+            bounds,
+            epsilon,
+            max_steps,
+            material,
+        }
+    }
+
+    /// Gradient of the field by central differences.
+    fn normal(self: &Self, p: Point3) -> Vec3 {
+        let h = self.epsilon;
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+
+        Vec3::new(
+            (self.distance)(p + dx) - (self.distance)(p - dx),
+            (self.distance)(p + dy) - (self.distance)(p - dy),
+            (self.distance)(p + dz) - (self.distance)(p - dz),
+        ).unit_vector()
+    }
+}
+
+impl<F: Fn(Point3) -> f64> Hittable for SdfObject<F> {
+    fn hit(self: &Self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let (t_start, t_end) = match self.bounds.hit_interval(ray, t_min, t_max) {
+            Some(interval) => interval,
+            None => return false,
+        };
+
+        let ray_length = ray.direction.length();
+        let mut t = t_start;
+
+        // Rays leaving the surface start within `epsilon` of it, and at a
+        // grazing angle would find it again straight away. Step them clear
+        // before looking for a hit.
+        let mut leaving = t_start <= t_min && (self.distance)(ray.at(t)).abs() < self.epsilon;
+
+        for _ in 0..self.max_steps {
+            let p = ray.at(t);
+
+            // Step by the absolute distance, so rays starting inside the
+            // surface find their way out just the same.
+            let d = (self.distance)(p).abs();
+            if leaving {
+                match d < self.epsilon {
+                    true => {
+                        t += self.epsilon / ray_length;
+                        if t > t_end {
+                            return false;
+                        }
+                        continue;
+                    }
+                    false => leaving = false,
+                }
+            }
+
+            if d < self.epsilon {
+                rec.t = t;
+                rec.p = p;
+
+                let outward_normal = self.normal(p);
+                rec.set_face_normal(ray, &outward_normal);
+
+                // Spherical mapping of the normal, with tangents as for a
+                // unit sphere with that normal.
+                rec.u = ((-outward_normal.z).atan2(outward_normal.x) + PI) / (2.0 * PI);
+                rec.v = (-outward_normal.y).clamp(-1.0, 1.0).acos() / PI;
+                (rec.dpdu, rec.dpdv) = Sphere::get_sphere_tangents(&outward_normal, 1.0);
+                rec.material = self.material.clone();
+
+                return true;
+            }
+
+            t += d / ray_length;
+            if t > t_end {
+                return false;
+            }
+        }
+
+        false
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+
+// Primitives
+
+pub fn sphere(center: Point3, radius: f64) -> impl Fn(Point3) -> f64 {
+    move |p| (p - center).length() - radius
+}
+
+/// A box with `half_extents` along each axis and its edges rounded off by `radius`.
+pub fn round_box(center: Point3, half_extents: Vec3, radius: f64) -> impl Fn(Point3) -> f64 {
+    move |p| {
+        let q = p - center;
+        let q = Vec3::new(q.x.abs() - half_extents.x, q.y.abs() - half_extents.y, q.z.abs() - half_extents.z);
+        let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+        outside + inside - radius
+    }
+}
+
+/// A ring lying flat in the `xz` plane.
+pub fn torus(center: Point3, major_radius: f64, minor_radius: f64) -> impl Fn(Point3) -> f64 {
+    move |p| {
+        let q = p - center;
+        let ring = (q.x * q.x + q.z * q.z).sqrt() - major_radius;
+        (ring * ring + q.y * q.y).sqrt() - minor_radius
+    }
+}
+
+/// A line segment from `a` to `b`, thickened by `radius`.
+pub fn capsule(a: Point3, b: Point3, radius: f64) -> impl Fn(Point3) -> f64 {
+    move |p| {
+        let pa = p - a;
+        let ba = b - a;
+        let h = (pa.dot(&ba) / ba.dot(&ba)).clamp(0.0, 1.0);
+        (pa - h * ba).length() - radius
+    }
+}
+
+/// The power 8 Mandelbulb, fitting in a sphere of about `scale`.
+/// More `iterations` give finer detail.
+pub fn mandelbulb(center: Point3, scale: f64, iterations: u32) -> impl Fn(Point3) -> f64 {
+    const POWER: f64 = 8.0;
+
+    move |p| {
+        let c = (p - center) / scale;
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = 0.0;
+
+        for _ in 0..iterations {
+            r = z.length();
+            if r > 2.0 {
+                break;
+            }
+
+            let theta = (z.z / r).acos() * POWER;
+            let phi = z.y.atan2(z.x) * POWER;
+            dr = r.powf(POWER - 1.0) * POWER * dr + 1.0;
+
+            let zr = r.powf(POWER);
+            z = zr * Vec3::new(theta.sin() * phi.cos(), phi.sin() * theta.sin(), theta.cos()) + c;
+        }
+
+        if r == 0.0 {
+            return -scale;
+        }
+        0.5 * r.ln() * r / dr * scale
+    }
+}
+
+
+// Combinators
+
+pub fn union(a: impl Fn(Point3) -> f64, b: impl Fn(Point3) -> f64) -> impl Fn(Point3) -> f64 {
+    move |p| a(p).min(b(p))
+}
+
+pub fn intersection(a: impl Fn(Point3) -> f64, b: impl Fn(Point3) -> f64) -> impl Fn(Point3) -> f64 {
+    move |p| a(p).max(b(p))
+}
+
+/// `a` with `b` cut out of it.
+pub fn difference(a: impl Fn(Point3) -> f64, b: impl Fn(Point3) -> f64) -> impl Fn(Point3) -> f64 {
+    move |p| a(p).max(-b(p))
+}
+
+/// Union with the seam blended over a distance of about `k`.
+pub fn smooth_union(a: impl Fn(Point3) -> f64, b: impl Fn(Point3) -> f64, k: f64) -> impl Fn(Point3) -> f64 {
+    move |p| {
+        let (da, db) = (a(p), b(p));
+        let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+        db + (da - db) * h - k * h * (1.0 - h)
+    }
+}
+
+/// Difference with the cut edge blended over a distance of about `k`.
+pub fn smooth_difference(a: impl Fn(Point3) -> f64, b: impl Fn(Point3) -> f64, k: f64) -> impl Fn(Point3) -> f64 {
+    move |p| {
+        let (da, db) = (a(p), b(p));
+        let h = (0.5 - 0.5 * (da + db) / k).clamp(0.0, 1.0);
+        da + (-db - da) * h + k * h * (1.0 - h)
+    }
+}
+
+/// Move a shape by `offset`.
+pub fn translate(a: impl Fn(Point3) -> f64, offset: Vec3) -> impl Fn(Point3) -> f64 {
+    move |p| a(p - offset)
+}
+
+/// Grow a shape outwards by `radius`, rounding its corners.
+pub fn round(a: impl Fn(Point3) -> f64, radius: f64) -> impl Fn(Point3) -> f64 {
+    move |p| a(p) - radius
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialType;
+    use crate::vector::Color;
+
+    #[test]
+    fn test_sphere_trace_matches_sphere() {
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+        let bounds = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let object = SdfObject::new(sphere(Point3::new(0.0, 0.0, 0.0), 1.0), bounds, 1e-6, 128, material);
+
+        let mut rec = HitRecord::default();
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 2.0));
+        assert!(object.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert!((rec.t - 2.0).abs() < 1e-5);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_grazing_ray_leaves_its_surface() {
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+        let bounds = Aabb::new(Point3::new(-1.5, -1.5, -1.5), Point3::new(1.5, 1.5, 1.5));
+        let object = SdfObject::new(sphere(Point3::new(0.0, 0.0, 0.0), 1.0), bounds, 1e-4, 256, material);
+
+        // Off the sphere's surface, only just away from it.
+        let mut rec = HitRecord::default();
+        let ray = Ray::new(Point3::new(0.0, 0.0, -1.0), Vec3::new(1.0, 0.0, -0.05));
+        assert!(!object.hit(&ray, 0.001, f64::INFINITY, &mut rec), "hit itself at {}", rec.t);
+    }
+}
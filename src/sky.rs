@@ -0,0 +1,208 @@
+// The Preetham et al. (1999) analytic daylight model, "A Practical Analytic
+// Model for Daylight", plus a sun disk whose color follows the same turbidity.
+
+use std::f64::consts::PI;
+
+use crate::color::xyz_to_linear_srgb;
+use crate::onb::Onb;
+use crate::spectrum::RGB_WAVELENGTHS;
+use crate::utility::degrees_to_radians;
+use crate::vector::{Color, Vec3};
+
+/// The model works in kcd/m^2; this brings a clear day's zenith to roughly 0.1-0.5.
+const SKY_SCALE: f64 = 0.05;
+
+/// Luminance of the sun outside the atmosphere, in kcd/m^2.
+const SUN_LUMINANCE: f64 = 1.6e6;
+
+/// Angular radius of the sun as seen from the earth.
+const SUN_ANGULAR_RADIUS: f64 = 0.004_654;
+
+/// How often `sample` aims at the sun rather than the rest of the sky.
+const SUN_SAMPLE_PROBABILITY: f64 = 0.5;
+
+
+/// Coefficients of the Perez sky luminance distribution function.
+#[derive(Copy, Clone)]
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    /// `theta` is the angle from the zenith, `gamma` the angle from the sun.
+    fn value(self: &Self, cos_theta: f64, gamma: f64) -> f64 {
+        let cos_gamma = gamma.cos();
+        (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+
+/// A procedural sky, meant to be used as the world background through
+/// `Environment::Sky`.
+pub struct PhysicalSky {
+    sun_direction: Vec3,
+
+    /// Perez coefficients for luminance `Y` and chromaticity `x`, `y`.
+    perez: [Perez; 3],
+
+    /// `Yxy` at the zenith divided by the Perez function there, so that
+    /// `perez * zenith` gives the sky value in any direction.
+    zenith: [f64; 3],
+
+    sun_radiance: Color,
+    cos_sun_radius: f64,
+    intensity: f64,
+}
+
+impl PhysicalSky {
+    /// `elevation` is the sun's angle above the horizon and `azimuth` its
+    /// angle around `+y`, measured from `+x` towards `+z`, both in degrees.
+    /// `turbidity` ranges from about 2 (very clear) to 10 (hazy).
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64, intensity: f64) -> Self {
+        let elevation = degrees_to_radians(elevation);
+        let azimuth = degrees_to_radians(azimuth);
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.cos(),
+            elevation.sin(),
+            elevation.cos() * azimuth.sin(),
+        );
+
+        let t = turbidity;
+        let perez = [
+            Perez { a: 0.1787 * t - 1.4630, b: -0.3554 * t + 0.4275, c: -0.0227 * t + 5.3251, d: 0.1206 * t - 2.5771, e: -0.0670 * t + 0.3703 },
+            Perez { a: -0.0193 * t - 0.2592, b: -0.0665 * t + 0.0008, c: -0.0004 * t + 0.2125, d: -0.0641 * t - 0.8989, e: -0.0033 * t + 0.0452 },
+            Perez { a: -0.0167 * t - 0.2608, b: -0.0950 * t + 0.0092, c: -0.0079 * t + 0.2102, d: -0.0441 * t - 1.6537, e: -0.0109 * t + 0.0529 },
+        ];
+
+        // The model is only fit for a sun above the horizon.
+        let theta_s = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0);
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let theta_s2 = theta_s * theta_s;
+        let theta_s3 = theta_s2 * theta_s;
+        let zenith_x = t * t * (0.00166 * theta_s3 - 0.00375 * theta_s2 + 0.00209 * theta_s)
+            + t * (-0.02903 * theta_s3 + 0.06377 * theta_s2 - 0.03202 * theta_s + 0.00394)
+            + (0.11693 * theta_s3 - 0.21196 * theta_s2 + 0.06052 * theta_s + 0.25886);
+        let zenith_y = t * t * (0.00275 * theta_s3 - 0.00610 * theta_s2 + 0.00317 * theta_s)
+            + t * (-0.04214 * theta_s3 + 0.08970 * theta_s2 - 0.04153 * theta_s + 0.00516)
+            + (0.15346 * theta_s3 - 0.26756 * theta_s2 + 0.06670 * theta_s + 0.26688);
+
+        let zenith_values = [zenith_luminance, zenith_x, zenith_y];
+        let mut zenith = [0.0; 3];
+        for i in 0..3 {
+            zenith[i] = zenith_values[i] / perez[i].value(1.0, theta_s);
+        }
+
+        PhysicalSky {
+            sun_direction,
+            perez,
+            zenith,
+            sun_radiance: sun_radiance(theta_s, t),
+            cos_sun_radius: SUN_ANGULAR_RADIUS.cos(),
+            intensity,
+        }
+    }
+
+    fn sun_visible(self: &Self) -> bool {
+        self.sun_direction.y > 0.0
+    }
+
+    /// Sky plus sun radiance arriving from `direction`.
+    /// There is nothing below the horizon.
+    pub fn value(self: &Self, direction: &Vec3) -> Color {
+        let d = direction.unit_vector();
+        if d.y <= 0.0 {
+            return Color::default();
+        }
+
+        let mut radiance = self.sky(&d);
+        if self.sun_visible() && d.dot(&self.sun_direction) >= self.cos_sun_radius {
+            radiance += self.sun_radiance;
+        }
+
+        self.intensity * radiance
+    }
+
+    fn sky(self: &Self, d: &Vec3) -> Color {
+        // Keep away from the horizon, where 1 / cos(theta) blows up.
+        let cos_theta = d.y.max(0.01);
+        let gamma = d.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let luminance = self.zenith[0] * self.perez[0].value(cos_theta, gamma);
+        let x = self.zenith[1] * self.perez[1].value(cos_theta, gamma);
+        let y = self.zenith[2] * self.perez[2].value(cos_theta, gamma);
+
+        let xyz = Vec3::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        SKY_SCALE * xyz_to_linear_srgb(&xyz)
+    }
+
+    /// Returns `(direction, radiance, pdf)`, aiming at the sun disk half of
+    /// the time and at the upper hemisphere otherwise.
+    pub fn sample(self: &Self, u0: f64, u1: f64, u2: f64) -> Option<(Vec3, Color, f64)> {
+        let direction = match self.sun_visible() && u2 < SUN_SAMPLE_PROBABILITY {
+            true => {
+                let cos_theta = 1.0 - u0 * (1.0 - self.cos_sun_radius);
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let phi = 2.0 * PI * u1;
+                Onb::build_from_w(&self.sun_direction).local(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+            }
+            false => {
+                let cos_theta = u0;
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let phi = 2.0 * PI * u1;
+                Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin())
+            }
+        };
+
+        let pdf = self.pdf(&direction);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some((direction, self.value(&direction), pdf))
+    }
+
+    pub fn pdf(self: &Self, direction: &Vec3) -> f64 {
+        let d = direction.unit_vector();
+        if d.y <= 0.0 {
+            return 0.0;
+        }
+
+        if !self.sun_visible() {
+            return 1.0 / (2.0 * PI);
+        }
+
+        let mut pdf = (1.0 - SUN_SAMPLE_PROBABILITY) / (2.0 * PI);
+        if d.dot(&self.sun_direction) >= self.cos_sun_radius {
+            pdf += SUN_SAMPLE_PROBABILITY / (2.0 * PI * (1.0 - self.cos_sun_radius));
+        }
+        pdf
+    }
+}
+
+/// Radiance of the sun disk after Rayleigh and aerosol extinction along the
+/// path through the atmosphere, for a sun `theta_s` away from the zenith.
+fn sun_radiance(theta_s: f64, turbidity: f64) -> Color {
+    // Relative optical mass (Kasten and Young).
+    let theta_degrees = theta_s.to_degrees();
+    let mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_degrees).powf(-1.253));
+
+    // Angstrom's turbidity formula for aerosols.
+    let beta = 0.04608 * turbidity - 0.04586;
+    let alpha = 1.3;
+
+    let transmittance = |lambda: f64| {
+        let rayleigh = (-0.008735 * mass * lambda.powf(-4.08)).exp();
+        let aerosol = (-beta * mass * lambda.powf(-alpha)).exp();
+        rayleigh * aerosol
+    };
+
+    // The formulas take wavelengths in micrometers.
+    let [red, green, blue] = RGB_WAVELENGTHS.map(|lambda| transmittance(lambda / 1000.0));
+    SKY_SCALE * SUN_LUMINANCE * Color::new(red, green, blue)
+}
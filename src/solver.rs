@@ -0,0 +1,153 @@
+// Closed form polynomial root finders, after Jochen Schwarze's "Cubic and
+// Quartic Roots" in Graphics Gems (1990). Coefficients are passed highest
+// degree first and only real roots are returned, in no particular order.
+
+const EPSILON: f64 = 1e-9;
+
+fn is_zero(x: f64) -> bool {
+    x.abs() < EPSILON
+}
+
+/// Roots of `a x^2 + b x + c`.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let p = b / (2.0 * a);
+    let q = c / a;
+    let discriminant = p * p - q;
+
+    if is_zero(discriminant) {
+        vec![-p]
+    } else if discriminant < 0.0 {
+        vec![]
+    } else {
+        let sqrt_d = discriminant.sqrt();
+        vec![sqrt_d - p, -sqrt_d - p]
+    }
+}
+
+/// Roots of `a x^3 + b x^2 + c x + d`.
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // Normal form x^3 + Ax^2 + Bx + C, then substitute x = y - A/3 to
+    // eliminate the quadratic term: y^3 + 3py + 2q = 0.
+    let a_ = b / a;
+    let b_ = c / a;
+    let c_ = d / a;
+
+    let sq_a = a_ * a_;
+    let p = (1.0 / 3.0) * (-(1.0 / 3.0) * sq_a + b_);
+    let q = 0.5 * ((2.0 / 27.0) * a_ * sq_a - (1.0 / 3.0) * a_ * b_ + c_);
+
+    let cb_p = p * p * p;
+    let discriminant = q * q + cb_p;
+
+    let mut roots = if is_zero(discriminant) {
+        if is_zero(q) {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if discriminant < 0.0 {
+        // Three real roots.
+        let phi = (1.0 / 3.0) * (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos();
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + std::f64::consts::PI / 3.0).cos(),
+            -t * (phi - std::f64::consts::PI / 3.0).cos(),
+        ]
+    } else {
+        let sqrt_d = discriminant.sqrt();
+        vec![(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt()]
+    };
+
+    for root in roots.iter_mut() {
+        *root -= a_ / 3.0;
+    }
+    roots
+}
+
+/// Roots of `a x^4 + b x^3 + c x^2 + d x + e`.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    // Normal form x^4 + Ax^3 + Bx^2 + Cx + D, then substitute x = y - A/4 to
+    // eliminate the cubic term: y^4 + py^2 + qy + r = 0.
+    let a_ = b / a;
+    let b_ = c / a;
+    let c_ = d / a;
+    let d_ = e / a;
+
+    let sq_a = a_ * a_;
+    let p = -(3.0 / 8.0) * sq_a + b_;
+    let q = (1.0 / 8.0) * sq_a * a_ - 0.5 * a_ * b_ + c_;
+    let r = -(3.0 / 256.0) * sq_a * sq_a + (1.0 / 16.0) * sq_a * b_ - 0.25 * a_ * c_ + d_;
+
+    let mut roots = if is_zero(r) {
+        // No absolute term: y (y^3 + py + q) = 0
+        let mut roots = solve_cubic(1.0, 0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // Take one root of the resolvent cubic...
+        let z = solve_cubic(1.0, -0.5 * p, -r, 0.5 * r * p - (1.0 / 8.0) * q * q)[0];
+
+        // ...and use it to split the quartic into two quadratics.
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+
+        let u = if is_zero(u) { 0.0 } else if u > 0.0 { u.sqrt() } else { return vec![] };
+        let v = if is_zero(v) { 0.0 } else if v > 0.0 { v.sqrt() } else { return vec![] };
+
+        let mut roots = solve_quadratic(1.0, if q < 0.0 { -v } else { v }, z - u);
+        roots.extend(solve_quadratic(1.0, if q < 0.0 { v } else { -v }, z + u));
+        roots
+    };
+
+    for root in roots.iter_mut() {
+        *root -= a_ / 4.0;
+
+        // The closed form loses precision, so polish each root with a couple
+        // of Newton steps on the original polynomial.
+        for _ in 0..2 {
+            let x = *root;
+            let f = (((a * x + b) * x + c) * x + d) * x + e;
+            let df = ((4.0 * a * x + 3.0 * b) * x + 2.0 * c) * x + d;
+            if df.abs() > EPSILON {
+                *root = x - f / df;
+            }
+        }
+    }
+    roots
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut roots: Vec<f64>) -> Vec<f64> {
+        roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        roots
+    }
+
+    fn assert_roots(actual: Vec<f64>, expected: &[f64]) {
+        let actual = sorted(actual);
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_solve_cubic() {
+        // (x - 1)(x - 2)(x + 3)
+        assert_roots(solve_cubic(1.0, 0.0, -7.0, 6.0), &[-3.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_solve_quartic() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0), &[1.0, 2.0, 3.0, 4.0]);
+
+        // (x^2 + 1)(x - 0.5)(x + 2) only has two real roots.
+        assert_roots(solve_quartic(2.0, 3.0, 0.0, 3.0, -2.0), &[-2.0, 0.5]);
+    }
+}
@@ -0,0 +1,261 @@
+// Spectral rendering by hero wavelength sampling (Wilkie et al., "Hero
+// Wavelength Spectral Sampling", 2014). A path carries its radiance at three
+// wavelengths in place of red, green and blue, so the integrator's `Color`s
+// work unchanged; only the scene's RGB inputs need turning into spectra.
+
+use std::sync::OnceLock;
+
+use crate::color::SRGB_FROM_XYZ;
+use crate::vector::{Color, Vec3};
+
+
+/// The range of wavelengths sampled, in nm.
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 720.0;
+
+/// Wavelengths per path.
+const LANES: usize = 3;
+
+/// Wavelengths, in nm, that stand in for red, green and blue when
+/// rendering in RGB something only known spectrally.
+pub const RGB_WAVELENGTHS: [f64; 3] = [630.0, 532.0, 465.0];
+
+
+/// What the three components of the `Color`s along a path stand for.
+#[derive(Copy, Clone)]
+pub enum Channels {
+    Rgb,
+    Spectral(SampledWavelengths),
+}
+
+impl Channels {
+    /// Turn an RGB quantity from the scene into the path's channels.
+    pub fn lift(self: &Self, rgb: Color) -> Color {
+        match self {
+            Channels::Rgb => rgb,
+            Channels::Spectral(wavelengths) => wavelengths.upsample(rgb),
+        }
+    }
+
+    /// The wavelength, in nm, that decides directions which depend on it.
+    pub fn wavelength(self: &Self) -> Option<f64> {
+        match self {
+            Channels::Rgb => None,
+            Channels::Spectral(wavelengths) => Some(wavelengths.hero()),
+        }
+    }
+
+    /// The wavelength, in nm, of each channel. RGB channels stand in for
+    /// `RGB_WAVELENGTHS`.
+    pub fn wavelengths(self: &Self) -> [f64; 3] {
+        match self {
+            Channels::Rgb => RGB_WAVELENGTHS,
+            Channels::Spectral(wavelengths) => wavelengths.lambda,
+        }
+    }
+
+    /// See `SampledWavelengths::terminate_secondary`.
+    pub fn terminate_secondary(self: &mut Self) {
+        if let Channels::Spectral(wavelengths) = self {
+            wavelengths.terminate_secondary();
+        }
+    }
+
+    /// Linear sRGB for a path's `radiance`.
+    pub fn to_rgb(self: &Self, radiance: Color) -> Color {
+        match self {
+            Channels::Rgb => radiance,
+            Channels::Spectral(wavelengths) => wavelengths.to_rgb(radiance),
+        }
+    }
+}
+
+
+/// The wavelengths, in nm, a path carries radiance at. The first is the
+/// hero; the others are spaced evenly after it, wrapping around the range.
+#[derive(Copy, Clone)]
+pub struct SampledWavelengths {
+    lambda: [f64; LANES],
+    pdf: [f64; LANES],
+}
+
+impl SampledWavelengths {
+    /// Put the hero wavelength `u` of the way along the range.
+    pub fn sample(u: f64) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let mut lambda = [0.0; LANES];
+
+        for (i, l) in lambda.iter_mut().enumerate() {
+            let offset = u * range + i as f64 * range / LANES as f64;
+            *l = LAMBDA_MIN + offset % range;
+        }
+
+        SampledWavelengths { lambda, pdf: [1.0 / range; LANES] }
+    }
+
+    pub fn hero(self: &Self) -> f64 {
+        self.lambda[0]
+    }
+
+    /// Keep only the hero wavelength, once the path has gone somewhere
+    /// only it could go, like through a prism.
+    pub fn terminate_secondary(self: &mut Self) {
+        if self.pdf[1] == 0.0 {
+            return;
+        }
+
+        for pdf in self.pdf.iter_mut().skip(1) {
+            *pdf = 0.0;
+        }
+        self.pdf[0] /= LANES as f64;
+    }
+
+    /// A smooth spectrum with (linear sRGB) color `rgb`, at each wavelength.
+    pub fn upsample(self: &Self, rgb: Color) -> Color {
+        let weights = transform(&calibration().weights_from_rgb, &rgb);
+        let value = |lambda: f64| {
+            let b = basis(lambda);
+            (weights.x * b[0] + weights.y * b[1] + weights.z * b[2]).max(0.0)
+        };
+
+        Color::new(value(self.lambda[0]), value(self.lambda[1]), value(self.lambda[2]))
+    }
+
+    /// Linear sRGB estimate from `radiance` at each wavelength. Single
+    /// wavelengths are mostly out of gamut, so this is often negative in
+    /// places; only sums of many estimates make sense as colors.
+    pub fn to_rgb(self: &Self, radiance: Color) -> Color {
+        let calibration = calibration();
+
+        let mut xyz = Vec3::default();
+        for i in 0..LANES {
+            if self.pdf[i] > 0.0 {
+                xyz += color_matching(self.lambda[i]) * (radiance[i] / self.pdf[i]);
+            }
+        }
+        xyz /= LANES as f64 * calibration.y_integral;
+
+        let rgb = transform(&SRGB_FROM_XYZ, &xyz);
+        Color::new(rgb.x / calibration.white.x, rgb.y / calibration.white.y, rgb.z / calibration.white.z)
+    }
+}
+
+
+/// CIE 1931 2° color matching functions, by the multi-lobe fit from Wyman,
+/// Sloan and Shirley, "Simple Analytic Approximations to the CIE XYZ Color
+/// Matching Functions" (2013).
+pub fn color_matching(lambda: f64) -> Vec3 {
+    let g = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let t = (lambda - mu) / if lambda < mu { sigma_below } else { sigma_above };
+        (-0.5 * t * t).exp()
+    };
+
+    Vec3::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// Smooth red, green and blue spectra that RGB colors are built from. They
+/// sum to one everywhere, so white becomes a flat spectrum.
+fn basis(lambda: f64) -> [f64; 3] {
+    let step = |edge: f64| 1.0 / (1.0 + (-(lambda - edge) / 10.0).exp());
+    let red = step(590.0);
+    let blue = 1.0 - step(490.0);
+    [red, 1.0 - red - blue, blue]
+}
+
+
+struct Calibration {
+    /// RGB to basis weights, chosen so upsampled colors come back out as
+    /// they went in.
+    weights_from_rgb: [[f64; 3]; 3],
+
+    /// Linear sRGB of a flat unit spectrum, which should come out white.
+    white: Color,
+
+    /// The integral of `ȳ` over the range, so a flat unit spectrum has `Y = 1`.
+    y_integral: f64,
+}
+
+fn calibration() -> &'static Calibration {
+    static CALIBRATION: OnceLock<Calibration> = OnceLock::new();
+
+    CALIBRATION.get_or_init(|| {
+        let mut y_integral = 0.0;
+        let mut basis_xyz = [Vec3::default(); 3];
+
+        // Midpoint rule, 1 nm at a time.
+        let mut lambda = LAMBDA_MIN + 0.5;
+        while lambda < LAMBDA_MAX {
+            let cmf = color_matching(lambda);
+            y_integral += cmf.y;
+            for (xyz, b) in basis_xyz.iter_mut().zip(basis(lambda)) {
+                *xyz += b * cmf;
+            }
+            lambda += 1.0;
+        }
+
+        let basis_rgb = basis_xyz.map(|xyz| transform(&SRGB_FROM_XYZ, &(xyz / y_integral)));
+        let white = basis_rgb[0] + basis_rgb[1] + basis_rgb[2];
+
+        // Columns are the white balanced colors of each basis spectrum.
+        let mut rgb_from_weights = [[0.0; 3]; 3];
+        for (k, rgb) in basis_rgb.iter().enumerate() {
+            for row in 0..3 {
+                rgb_from_weights[row][k] = rgb[row] / white[row];
+            }
+        }
+
+        Calibration {
+            weights_from_rgb: invert(&rgb_from_weights),
+            white,
+            y_integral,
+        }
+    })
+}
+
+fn transform(m: &[[f64; 3]; 3], v: &Vec3) -> Vec3 {
+    let row = |r: &[f64; 3]| r[0] * v.x + r[1] * v.y + r[2] * v.z;
+    Vec3::new(row(&m[0]), row(&m[1]), row(&m[2]))
+}
+
+fn invert(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let cofactor = |r: usize, c: usize| {
+        let (r0, r1) = ((r + 1) % 3, (r + 2) % 3);
+        let (c0, c1) = ((c + 1) % 3, (c + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+
+    let determinant: f64 = (0..3).map(|c| m[0][c] * cofactor(0, c)).sum();
+
+    let mut inverse = [[0.0; 3]; 3];
+    for (r, row) in inverse.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = cofactor(c, r) / determinant;
+        }
+    }
+    inverse
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsampling_round_trip() {
+        for rgb in [Color::new(1.0, 1.0, 1.0), Color::new(0.8, 0.3, 0.1), Color::new(0.1, 0.5, 0.9)] {
+            let n = 1000;
+            let mut sum = Color::default();
+            for i in 0..n {
+                let wavelengths = SampledWavelengths::sample((i as f64 + 0.5) / n as f64);
+                sum += wavelengths.to_rgb(wavelengths.upsample(rgb));
+            }
+
+            let result = sum / n as f64;
+            assert!((result - rgb).length() < 0.01, "{} != {}", result, rgb);
+        }
+    }
+}
@@ -1,9 +1,15 @@
+use std::f64::consts::PI;
+
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::{Vec3, Point3};
 use crate::hittable::{HitRecord, Hittable};
 use crate::Ray;
 
 
+/// A negative `radius` turns the sphere inside out, with normals pointing
+/// towards the center. Inside a larger sphere of the same dielectric, it
+/// makes a hollow bubble.
 pub struct Sphere {
     center: Point3,
     radius: f64,
@@ -18,6 +24,29 @@ impl Sphere {
             material
         }
     }
+
+    /// `p` is a point on the unit sphere. `u` goes around the `y` axis from
+    /// `-x`, `v` from the bottom pole to the top one.
+    fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Derivatives of the point with `u` and `v`, for `p` on the unit
+    /// sphere, as for `get_sphere_uv`. Zero at the poles, where they
+    /// aren't defined.
+    pub fn get_sphere_tangents(p: &Point3, radius: f64) -> (Vec3, Vec3) {
+        let sin_theta = (p.x * p.x + p.z * p.z).sqrt();
+        if sin_theta < 1e-6 {
+            return (Vec3::default(), Vec3::default());
+        }
+
+        let dpdu = 2.0 * PI * radius * Vec3::new(p.z, 0.0, -p.x);
+        let dpdv = PI * radius * Vec3::new(-p.x * p.y / sin_theta, sin_theta, -p.y * p.z / sin_theta);
+        (dpdu, dpdv)
+    }
 }
 
 impl Hittable for Sphere {
@@ -33,7 +62,7 @@ impl Hittable for Sphere {
         let dis_sqrt = discriminant.sqrt();
         let mut root = (-half_b - dis_sqrt) / a;
         if root < t_min || root > t_max {
-            root = (-half_b - dis_sqrt) / a;
+            root = (-half_b + dis_sqrt) / a;
             if root < t_min || root > t_max { return false; }
         }
 
@@ -42,8 +71,101 @@ impl Hittable for Sphere {
 
         let outward_normal: Vec3 = (rec.p - self.center) / self.radius;
         rec.set_face_normal(ray, &outward_normal);
-        rec.material = self.material;
+        let unit = (rec.p - self.center) / self.radius.abs();
+        (rec.u, rec.v) = Sphere::get_sphere_uv(&unit);
+        (rec.dpdu, rec.dpdv) = Sphere::get_sphere_tangents(&unit, self.radius.abs());
+        rec.material = self.material.clone();
 
         return true;
     }
+
+    fn hit_all(self: &Self, ray: &Ray, hits: &mut Vec<HitRecord>) {
+        let oc: Vec3 = ray.origin - self.center;
+        let a = ray.direction.length_squared();
+        let half_b = oc.dot(&ray.direction);
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = (half_b * half_b) - (a * c);
+
+        if discriminant <= 0.0 { return; }
+
+        let dis_sqrt = discriminant.sqrt();
+        for root in [(-half_b - dis_sqrt) / a, (-half_b + dis_sqrt) / a] {
+            let mut rec = HitRecord::default();
+            rec.t = root;
+            rec.p = ray.at(root);
+
+            let outward_normal: Vec3 = (rec.p - self.center) / self.radius;
+            rec.set_face_normal(ray, &outward_normal);
+            let unit = (rec.p - self.center) / self.radius.abs();
+            (rec.u, rec.v) = Sphere::get_sphere_uv(&unit);
+            (rec.dpdu, rec.dpdv) = Sphere::get_sphere_tangents(&unit, self.radius.abs());
+            rec.material = self.material.clone();
+
+            hits.push(rec);
+        }
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        let radius = self.radius.abs();
+        let extent = Vec3::new(radius, radius, radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialType;
+    use crate::vector::Color;
+
+    /// The point on a sphere of `radius` at `(u, v)`, undoing `get_sphere_uv`.
+    fn point(u: f64, v: f64, radius: f64) -> Point3 {
+        let (theta, phi) = (v * PI, u * 2.0 * PI);
+        radius * Point3::new(-theta.sin() * phi.cos(), -theta.cos(), theta.sin() * phi.sin())
+    }
+
+    fn hit(sphere: &Sphere, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        let mut rec = HitRecord::default();
+        match sphere.hit(&Ray::new(origin, direction), 0.001, f64::INFINITY, &mut rec) {
+            true => Some(rec),
+            false => None,
+        }
+    }
+
+    #[test]
+    fn test_hit_from_inside() {
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5)));
+        let center = Point3::new(1.0, 2.0, 3.0);
+
+        // The near root is behind the ray, so it takes the far one.
+        let sphere = Sphere::new(center, 2.0, material.clone());
+        let rec = hit(&sphere, center, Vec3::new(0.0, 0.0, 1.0)).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-9 && !rec.front_face);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-9);
+
+        // Turned inside out, the inside is the front.
+        let bubble = Sphere::new(center, -2.0, material);
+        let rec = hit(&bubble, center + Vec3::new(0.5, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 1.5).abs() < 1e-9 && rec.front_face);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_tangents_match_finite_differences() {
+        let radius = 2.5;
+        let h = 1e-6;
+
+        for &(u, v) in &[(0.1, 0.3), (0.4, 0.5), (0.65, 0.2), (0.9, 0.85)] {
+            let p = point(u, v, radius) / radius;
+            let (pu, pv) = Sphere::get_sphere_uv(&p);
+            assert!((pu - u).abs() < 1e-9 && (pv - v).abs() < 1e-9);
+
+            let (dpdu, dpdv) = Sphere::get_sphere_tangents(&p, radius);
+            let du = (point(u + h, v, radius) - point(u - h, v, radius)) / (2.0 * h);
+            let dv = (point(u, v + h, radius) - point(u, v - h, radius)) / (2.0 * h);
+            assert!((dpdu - du).length() < 1e-5 * du.length(), "{} != {}", dpdu, du);
+            assert!((dpdv - dv).length() < 1e-5 * dv.length(), "{} != {}", dpdv, dv);
+        }
+    }
 }
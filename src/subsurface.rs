@@ -0,0 +1,137 @@
+// Subsurface scattering by random walk, much as in Cycles: light gets into
+// the material diffusely, scatters about a homogeneous medium inside until it
+// finds its way back to the surface, and leaves diffusely from there. Each
+// channel has its own mean free path, so a single walk is shared between
+// them by sampling distances for one channel at a time and weighting by the
+// average PDF over all of them.
+//
+// Only the way in and the first step take numbers from the sampler. Walks go
+// on for any number of steps, and the rest of the path should find its
+// sampler dimensions in the same place however long the walk was, so later
+// steps draw independent numbers instead.
+
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::{Material, MaterialType};
+use crate::phase::PhaseFunction;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::spectrum::Channels;
+use crate::utility::random_f64;
+use crate::vector::{Color, Vec3};
+
+
+/// Scattering events before a walk gives up. Long walks carry little light.
+const MAX_STEPS: u32 = 256;
+
+
+/// Follow light into the subsurface material at `rec`. Returns the ray that
+/// brings it back to the surface, where it comes out, as a white diffuse
+/// surface facing out of the material, and the walk's weight. `None` if it
+/// never gets out.
+///
+/// The walk comes out at whatever surface `world.hit` finds first, so where
+/// other objects overlap the material, their surfaces count as its boundary
+/// too.
+pub fn random_walk<T>(rec: &HitRecord, albedo: Color, mean_free_path: Color, phase: &PhaseFunction, world: &T, channels: &Channels, sampler: &mut Sampler) -> Option<(Ray, HitRecord, Color)>
+where T: Hittable
+{
+    let sigma_t = channels.lift(Color::new(1.0 / mean_free_path.x, 1.0 / mean_free_path.y, 1.0 / mean_free_path.z));
+    let sigma_s = sigma_t * channels.lift(albedo);
+
+    // In through a diffuse interface, which with cosine weighting leaves
+    // nothing to weight.
+    let mut direction = -rec.normal + Vec3::sample_unit_vector(sampler.get_2d());
+    if direction.near_zero() {
+        direction = -rec.normal;
+    }
+    let mut walk = Ray::new(rec.p, direction.unit_vector());
+    let mut weight = Color::new(1.0, 1.0, 1.0);
+
+    // Only the first step starts on a surface. Scattering happens inside,
+    // however close to the surface, so later steps take any hit.
+    let mut t_min = 0.001;
+
+    for step in 0..MAX_STEPS {
+        let (u1, u2) = match step {
+            0 => sampler.get_2d(),
+            _ => (random_f64(None, None), random_f64(None, None)),
+        };
+        let channel = ((u1 * 3.0) as usize).min(2);
+        let distance = -(1.0 - u2).ln() / sigma_t[channel];
+
+        let mut exit = HitRecord::default();
+        if world.hit(&walk, t_min, distance, &mut exit) {
+            // Made it back to the surface, having not scattered on the way.
+            let transmittance = exponential(sigma_t, exit.t);
+            weight *= transmittance / average(transmittance);
+
+            exit.normal = -exit.normal;
+            exit.front_face = true;
+            exit.material = Material::new(MaterialType::Lambertian(Color::new(1.0, 1.0, 1.0)));
+            return Some((walk, exit, weight));
+        }
+
+        let transmittance = exponential(sigma_t, distance);
+        weight *= sigma_s * transmittance / average(sigma_t * transmittance);
+        if weight.near_zero() {
+            return None;
+        }
+
+        let u = (random_f64(None, None), random_f64(None, None));
+        walk = Ray::new(walk.at(distance), phase.sample(&walk.direction.unit_vector(), random_f64(None, None), u));
+        t_min = 0.0;
+    }
+
+    None
+}
+
+fn exponential(sigma: Color, distance: f64) -> Color {
+    Color::new((-sigma.x * distance).exp(), (-sigma.y * distance).exp(), (-sigma.z * distance).exp())
+}
+
+fn average(c: Color) -> f64 {
+    (c.x + c.y + c.z) / 3.0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::SamplerKind;
+    use crate::sphere::Sphere;
+    use crate::world::World;
+
+    #[test]
+    fn test_white_walk_always_gets_out() {
+        let albedo = Color::new(1.0, 1.0, 1.0);
+        let mean_free_path = Color::new(0.2, 0.25, 0.3);
+        let material = Material::new(MaterialType::Subsurface(albedo, mean_free_path, PhaseFunction::Isotropic));
+
+        let mut world = World::new();
+        world.add(Box::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, material.clone())));
+
+        let mut rec = HitRecord::default();
+        rec.p = Vec3::new(0.0, 1.0, 0.0);
+        rec.normal = Vec3::new(0.0, 1.0, 0.0);
+        rec.front_face = true;
+        rec.material = material;
+
+        // Nothing is absorbed, so every walk gets out, and on average with
+        // all the light that went in.
+        let n = 4096;
+        let mut sampler = Sampler::new(SamplerKind::Sobol, n);
+        let mut total = Color::default();
+        for i in 0..n {
+            sampler.start_pixel_sample(0, 0, i);
+            let (_, exit, weight) = random_walk(&rec, albedo, mean_free_path, &PhaseFunction::Isotropic, &world, &Channels::Rgb, &mut sampler)
+                .expect("The walk should get out");
+            assert!((exit.p.length() - 1.0).abs() < 1e-6);
+            total += weight;
+        }
+
+        let mean = total / n as f64;
+        for c in 0..3 {
+            assert!((mean[c] - 1.0).abs() < 0.02, "{}", mean);
+        }
+    }
+}
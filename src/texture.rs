@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use crate::image_reader::FloatImage;
+use crate::vector::{Color, Point3};
+
+
+/// A color that varies over a surface.
+#[derive(Clone, PartialEq)]
+pub enum Texture {
+    Constant(Color),
+
+    /// Checker(even, odd, scale). Solid cubes `scale` across, alternating
+    /// in color, so it needs no texture co-ordinates.
+    Checker(Color, Color, f64),
+
+    /// Image(image). Wrapped around the surface by its `(u, v)`, with
+    /// `v = 0` at the bottom of the image. See `Texture::image`.
+    Image(Arc<FloatImage>),
+}
+
+impl Texture {
+    pub fn image(image: FloatImage) -> Self {
+        Texture::Image(Arc::new(image))
+    }
+
+    pub fn value(self: &Self, u: f64, v: f64, p: &Point3) -> Color {
+        match self {
+            Texture::Constant(c) => *c,
+            Texture::Checker(even, odd, scale) => {
+                let cell = |x: f64| (x / scale).floor() as i64;
+                match (cell(p.x) + cell(p.y) + cell(p.z)).rem_euclid(2) == 0 {
+                    true => *even,
+                    false => *odd,
+                }
+            }
+            Texture::Image(image) => {
+                // Bilinear between texel centers, repeating outside [0, 1),
+                // so bump maps have slopes inside texels too.
+                let x = u.rem_euclid(1.0) * image.width as f64 - 0.5;
+                let y = (1.0 - v.rem_euclid(1.0)) * image.height as f64 - 0.5;
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+
+                let texel = |i: f64, j: f64| {
+                    let i = (i as i64).rem_euclid(image.width as i64) as usize;
+                    let j = (j as i64).rem_euclid(image.height as i64) as usize;
+                    image.texels[j * image.width + i]
+                };
+                (1.0 - fy) * ((1.0 - fx) * texel(x0, y0) + fx * texel(x0 + 1.0, y0))
+                    + fy * ((1.0 - fx) * texel(x0, y0 + 1.0) + fx * texel(x0 + 1.0, y0 + 1.0))
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// Thin-film interference: the iridescence of soap bubbles, oil on water and
+// anodized metals. Light reflected off the top of a film a few hundred nm
+// thick interferes with light reflected off the bottom, so how much is
+// reflected depends on wavelength and angle. Summed over every bounce inside
+// the film, after Airy, the amplitude reflected is
+//
+//     r = (r12 + r23 e^{iδ}) / (1 + r12 r23 e^{iδ}),  δ = 4π n2 d cos θ2 / λ
+//
+// per polarization, with 1 the medium above, 2 the film and 3 below.
+
+use std::ops;
+
+use crate::vector::Color;
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ThinFilm {
+    /// In nm.
+    pub thickness: f64,
+    pub ior: f64,
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, ior: f64) -> Self {
+        ThinFilm { thickness, ior }
+    }
+
+    /// Reflectance at each of `wavelengths`, in nm, of the film between
+    /// dielectrics, for light arriving at `cos_theta` to the normal from a
+    /// medium of index `above` onto one of index `below`.
+    pub fn reflectance_dielectric(self: &Self, cos_theta: f64, above: f64, below: f64, wavelengths: [f64; 3]) -> Color {
+        let [r, g, b] = wavelengths.map(|lambda| self.reflectance(cos_theta, above, Complex::new(below, 0.0), lambda));
+        Color::new(r, g, b)
+    }
+
+    /// Reflectance at each of `wavelengths` of the film on a conductor with
+    /// complex refractive index `eta + i k` at each, as for
+    /// `fresnel_conductor`, under a medium of index `above`.
+    pub fn reflectance_conductor(self: &Self, cos_theta: f64, above: f64, eta: Color, k: Color, wavelengths: [f64; 3]) -> Color {
+        let channel = |i: usize| self.reflectance(cos_theta, above, Complex::new(eta[i], k[i]), wavelengths[i]);
+        Color::new(channel(0), channel(1), channel(2))
+    }
+
+    /// Unpolarized reflectance at wavelength `lambda`, in nm.
+    fn reflectance(self: &Self, cos_theta: f64, above: f64, below: Complex, lambda: f64) -> f64 {
+        let n1 = Complex::new(above, 0.0);
+        let n2 = Complex::new(self.ior, 0.0);
+        let cos1 = Complex::new(cos_theta.clamp(0.0, 1.0), 0.0);
+
+        // Snell's law holds n sin θ constant through every layer.
+        let sin1 = above * (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let cosine_in = |n: Complex| {
+            let s = Complex::new(sin1, 0.0) / n;
+            (Complex::new(1.0, 0.0) - s * s).sqrt()
+        };
+        let (cos2, cos3) = (cosine_in(n2), cosine_in(below));
+
+        let phase = Complex::new(4.0 * std::f64::consts::PI * self.thickness / lambda, 0.0) * n2 * cos2;
+        let shift = (Complex::new(0.0, 1.0) * phase).exp();
+
+        let airy = |r12: Complex, r23: Complex| {
+            let r = (r12 + r23 * shift) / (Complex::new(1.0, 0.0) + r12 * r23 * shift);
+            r.norm_sqr()
+        };
+
+        let s = airy(fresnel_s(n1, cos1, n2, cos2), fresnel_s(n2, cos2, below, cos3));
+        let p = airy(fresnel_p(n1, cos1, n2, cos2), fresnel_p(n2, cos2, below, cos3));
+        (0.5 * (s + p)).clamp(0.0, 1.0)
+    }
+}
+
+
+/// Fresnel amplitude coefficients, going from index `ni` to `nj`.
+fn fresnel_s(ni: Complex, cos_i: Complex, nj: Complex, cos_j: Complex) -> Complex {
+    (ni * cos_i - nj * cos_j) / (ni * cos_i + nj * cos_j)
+}
+
+fn fresnel_p(ni: Complex, cos_i: Complex, nj: Complex, cos_j: Complex) -> Complex {
+    (nj * cos_i - ni * cos_j) / (nj * cos_i + ni * cos_j)
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    fn norm_sqr(self: &Self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// The principal square root, with a non-negative real part.
+    fn sqrt(self: &Self) -> Self {
+        let r = self.norm_sqr().sqrt();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    fn exp(self: &Self) -> Self {
+        let magnitude = self.re.exp();
+        Complex::new(magnitude * self.im.cos(), magnitude * self.im.sin())
+    }
+}
+
+impl ops::Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl ops::Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl ops::Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl ops::Div for Complex {
+    type Output = Complex;
+
+    fn div(self, other: Complex) -> Complex {
+        let denominator = other.norm_sqr();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator,
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::microfacet::{fresnel_conductor, fresnel_dielectric};
+    use crate::spectrum::RGB_WAVELENGTHS;
+
+    #[test]
+    fn test_vanishing_film_is_plain_fresnel() {
+        let film = ThinFilm::new(0.0, 1.33);
+        let (eta, k) = (Color::new(0.2, 0.9, 1.1), Color::new(3.9, 2.4, 2.1));
+
+        for cos_theta in [1.0, 0.7, 0.3] {
+            let dielectric = film.reflectance_dielectric(cos_theta, 1.0, 1.5, RGB_WAVELENGTHS);
+            assert!((dielectric.x - fresnel_dielectric(cos_theta, 1.5)).abs() < 1e-9);
+
+            let conductor = film.reflectance_conductor(cos_theta, 1.0, eta, k, RGB_WAVELENGTHS);
+            assert!((conductor - fresnel_conductor(cos_theta, eta, k)).length() < 1e-6, "{} at {}", conductor, cos_theta);
+        }
+    }
+}
@@ -112,6 +112,11 @@ impl Vec3 {
         r_perpendicular + r_parallel
     }
 
+    /// Perceived brightness, treating the `Vec3` as a linear RGB color.
+    pub fn luminance(self: &Self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
     pub fn random_in_unit_disk() -> Self {
         loop {
             let p = Vec3::new(random_f64(Some(-1.0), Some(1.0)), random_f64(Some(-1.0), Some(1.0)), 0.0);
@@ -121,6 +126,26 @@ impl Vec3 {
             return p;
         }
     }
+
+    /// As `random_unit_vector`, but from a `Sampler`'s pair of numbers.
+    pub fn sample_unit_vector(u: (f64, f64)) -> Self {
+        let z = 1.0 - 2.0 * u.0;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * u.1;
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    /// As `random_in_unit_sphere`, with `w` picking the distance out.
+    pub fn sample_in_unit_sphere(u: (f64, f64), w: f64) -> Self {
+        w.cbrt() * Vec3::sample_unit_vector(u)
+    }
+
+    /// As `random_in_unit_disk`.
+    pub fn sample_in_unit_disk(u: (f64, f64)) -> Self {
+        let r = u.0.sqrt();
+        let phi = 2.0 * std::f64::consts::PI * u.1;
+        Vec3::new(r * phi.cos(), r * phi.sin(), 0.0)
+    }
 }
 
 
@@ -240,6 +265,17 @@ impl ops::Index<usize> for Vec3 {
     }
 }
 
+impl ops::IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Can't index into Vec3 with index: {}", index),
+        }
+    }
+}
+
 impl ops::AddAssign for Vec3 {
     fn add_assign(&mut self, rhs: Self) {
         self.x = self.x + rhs.x;
@@ -0,0 +1,183 @@
+use std::error;
+use std::fs;
+
+use crate::vector::Point3;
+
+/// Let's Box any errors!
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+
+/// Voxels per side of a brick.
+const BRICK: usize = 8;
+
+
+/// Densities on a regular grid, stored sparsely: the grid is split into
+/// bricks of 8³ voxels, and bricks that are empty aren't stored at all, so
+/// clouds and smoke surrounded by clear air stay small.
+pub struct VoxelGrid {
+    /// Voxels along x, y and z.
+    size: [usize; 3],
+
+    /// Bricks along x, y and z.
+    bricks_size: [usize; 3],
+
+    /// Indexed as `bricks[(z * bricks_size[1] + y) * bricks_size[0] + x]`,
+    /// with voxels inside each indexed the same way.
+    bricks: Vec<Option<Box<[f32]>>>,
+
+    max_value: f64,
+}
+
+impl VoxelGrid {
+    /// From every voxel's value, x varying fastest, then y, then z.
+    pub fn from_dense(nx: usize, ny: usize, nz: usize, values: &[f32]) -> Self {
+        assert_eq!(values.len(), nx * ny * nz, "Voxel grid size doesn't match its values");
+
+        let mut grid = VoxelGrid::empty(nx, ny, nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    grid.set(x, y, z, values[(z * ny + y) * nx + x]);
+                }
+            }
+        }
+        grid
+    }
+
+    /// From the voxels that aren't empty, as `(x, y, z, value)`.
+    pub fn from_sparse(nx: usize, ny: usize, nz: usize, voxels: &[(usize, usize, usize, f32)]) -> Self {
+        let mut grid = VoxelGrid::empty(nx, ny, nz);
+        for &(x, y, z, value) in voxels {
+            grid.set(x, y, z, value);
+        }
+        grid
+    }
+
+    /// Read a headerless file of `nx * ny * nz` voxels, ordered as for
+    /// `from_dense`. Each is either a byte, taken as a fraction of 255, or a
+    /// little-endian `f32`, going by the size of the file.
+    pub fn load_raw(path: &str, nx: usize, ny: usize, nz: usize) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let count = nx * ny * nz;
+
+        let values: Vec<f32> = if bytes.len() == count {
+            bytes.iter().map(|b| *b as f32 / 255.0).collect()
+        } else if bytes.len() == 4 * count {
+            bytes.chunks_exact(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect()
+        } else {
+            return Err(format!("{} is {} bytes, which isn't {} voxels of u8 or f32", path, bytes.len(), count).into());
+        };
+
+        Ok(VoxelGrid::from_dense(nx, ny, nz, &values))
+    }
+
+    /// Read a text file of the voxels that aren't empty, one `x y z value`
+    /// per line, for `from_sparse`. Blank lines and `#` comments are skipped.
+    pub fn load_sparse(path: &str, nx: usize, ny: usize, nz: usize) -> Result<Self> {
+        let mut voxels = Vec::new();
+        for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 {
+                return Err(format!("{}:{}: expected x y z value", path, number + 1).into());
+            }
+            let (x, y, z): (usize, usize, usize) = (fields[0].parse()?, fields[1].parse()?, fields[2].parse()?);
+            if x >= nx || y >= ny || z >= nz {
+                return Err(format!("{}:{}: voxel ({}, {}, {}) is outside the {}x{}x{} grid", path, number + 1, x, y, z, nx, ny, nz).into());
+            }
+            voxels.push((x, y, z, fields[3].parse()?));
+        }
+
+        Ok(VoxelGrid::from_sparse(nx, ny, nz, &voxels))
+    }
+
+    fn empty(nx: usize, ny: usize, nz: usize) -> Self {
+        let bricks_size = [nx.div_ceil(BRICK), ny.div_ceil(BRICK), nz.div_ceil(BRICK)];
+        VoxelGrid {
+            size: [nx, ny, nz],
+            bricks_size,
+            bricks: vec![None; bricks_size[0] * bricks_size[1] * bricks_size[2]],
+            max_value: 0.0,
+        }
+    }
+
+    fn set(self: &mut Self, x: usize, y: usize, z: usize, value: f32) {
+        if value == 0.0 {
+            return;
+        }
+
+        let (brick, voxel) = self.index(x, y, z);
+        let brick = self.bricks[brick].get_or_insert_with(|| vec![0.0; BRICK * BRICK * BRICK].into_boxed_slice());
+        brick[voxel] = value;
+        self.max_value = self.max_value.max(value as f64);
+    }
+
+    fn index(self: &Self, x: usize, y: usize, z: usize) -> (usize, usize) {
+        let brick = ((z / BRICK) * self.bricks_size[1] + y / BRICK) * self.bricks_size[0] + x / BRICK;
+        let voxel = ((z % BRICK) * BRICK + y % BRICK) * BRICK + x % BRICK;
+        (brick, voxel)
+    }
+
+    fn voxel(self: &Self, x: usize, y: usize, z: usize) -> f64 {
+        let (brick, voxel) = self.index(x, y, z);
+        match &self.bricks[brick] {
+            Some(values) => values[voxel] as f64,
+            None => 0.0,
+        }
+    }
+
+    /// The largest value anywhere, which bounds `lookup`.
+    pub fn max_value(self: &Self) -> f64 {
+        self.max_value
+    }
+
+    /// Trilinearly interpolated value at `p` in `[0, 1]³` across the grid,
+    /// with voxel values at the voxels' centers.
+    pub fn lookup(self: &Self, p: &Point3) -> f64 {
+        let mut cell = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let g = (p[axis] * self.size[axis] as f64 - 0.5).clamp(0.0, (self.size[axis] - 1) as f64);
+            cell[axis] = (g as usize).min(self.size[axis].saturating_sub(2));
+            fraction[axis] = g - cell[axis] as f64;
+        }
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut weight = 1.0;
+            let mut index = [0; 3];
+            for axis in 0..3 {
+                index[axis] = (cell[axis] + offset[axis]).min(self.size[axis] - 1);
+                weight *= if offset[axis] == 1 { fraction[axis] } else { 1.0 - fraction[axis] };
+            }
+            if weight > 0.0 {
+                value += weight * self.voxel(index[0], index[1], index[2]);
+            }
+        }
+        value
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_grid_lookup() {
+        // Two voxels in different bricks, everything else empty.
+        let grid = VoxelGrid::from_sparse(16, 16, 16, &[(7, 7, 7, 1.0), (8, 7, 7, 0.5)]);
+        assert_eq!(grid.bricks.iter().filter(|b| b.is_some()).count(), 2);
+        assert_eq!(grid.max_value(), 1.0);
+
+        let center = |x: usize| (x as f64 + 0.5) / 16.0;
+        assert_eq!(grid.lookup(&Point3::new(center(7), center(7), center(7))), 1.0);
+        assert_eq!(grid.lookup(&Point3::new(8.0 / 16.0, center(7), center(7))), 0.75);
+        assert_eq!(grid.lookup(&Point3::new(0.1, 0.9, 0.5)), 0.0);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::hittable::{hit_opaque, HitRecord, Hittable};
+use crate::ray::Ray;
+
+
+/// The top level container for a scene's objects.
+/// Once built, bounded objects live in a BVH, while unbounded ones (such as
+/// infinite planes) are kept aside and tested on every ray.
+pub struct World {
+    /// Objects added since the last `build`.
+    objects: Vec<Box<dyn Hittable + Send + Sync>>,
+    unbounded: Vec<Box<dyn Hittable + Send + Sync>>,
+    bvh: Option<BvhNode>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        World {
+            objects: vec![],
+            unbounded: vec![],
+            bvh: None,
+        }
+    }
+
+    pub fn add(self: &mut Self, object: Box<dyn Hittable + Send + Sync>) {
+        self.objects.push(object);
+    }
+
+    /// Put every bounded object into the BVH. Call this once the world is
+    /// complete; until then, objects are tested one by one.
+    pub fn build(self: &mut Self) {
+        let mut bounded: Vec<Box<dyn Hittable + Send + Sync>> = vec![];
+
+        for object in self.objects.drain(..) {
+            match object.bounding_box() {
+                Some(_) => bounded.push(object),
+                None => self.unbounded.push(object),
+            }
+        }
+
+        if let Some(bvh) = self.bvh.take() {
+            bounded.push(Box::new(bvh));
+        }
+        if !bounded.is_empty() {
+            self.bvh = Some(BvhNode::new(bounded));
+        }
+    }
+}
+
+impl Hittable for World {
+    fn hit(self: &Self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let mut temp_rec = HitRecord::default();
+        let mut hit_once = false;
+        let mut closest_so_far = t_max;
+
+        if let Some(bvh) = &self.bvh {
+            if bvh.hit(r, t_min, closest_so_far, &mut temp_rec) {
+                hit_once = true;
+                closest_so_far = temp_rec.t;
+                *rec = temp_rec.clone();
+            }
+        }
+
+        for object in self.unbounded.iter().chain(self.objects.iter()) {
+            if hit_opaque(object, r, t_min, closest_so_far, &mut temp_rec) {
+                hit_once = true;
+                closest_so_far = temp_rec.t;
+                *rec = temp_rec.clone();
+            }
+        }
+
+        hit_once
+    }
+
+    fn transmittance(self: &Self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = match &self.bvh {
+            Some(bvh) => bvh.transmittance(r, t_min, t_max),
+            None => 1.0,
+        };
+
+        for object in self.unbounded.iter().chain(self.objects.iter()) {
+            if transmittance == 0.0 {
+                break;
+            }
+            transmittance *= object.transmittance(r, t_min, t_max);
+        }
+
+        transmittance
+    }
+
+    fn bounding_box(self: &Self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+
+        let mut boxes = self.objects.iter().map(|o| o.bounding_box()).chain(self.bvh.iter().map(|b| b.bounding_box()));
+        let first = boxes.next()??;
+        boxes.try_fold(first, |a, b| Some(Aabb::surrounding_box(&a, &b?)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{Material, MaterialType};
+    use crate::quad::Quad;
+    use crate::sphere::Sphere;
+    use crate::texture::Texture;
+    use crate::vector::{Color, Point3, Vec3};
+
+    /// A unit quad across the ray at `z`, as much there as `alpha`.
+    fn sheet(z: f64, alpha: f64) -> Box<Quad> {
+        let material = Material::new(MaterialType::Lambertian(Color::new(0.5, 0.5, 0.5))).with_alpha(Texture::Constant(Color::new(alpha, alpha, alpha)));
+        Box::new(Quad::new(Point3::new(-1.0, -1.0, z), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), material))
+    }
+
+    #[test]
+    fn test_hit_through_cut_out() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let ball = Material::new(MaterialType::Metal(Color::new(0.9, 0.9, 0.9), 0.0));
+
+        // Tested one by one, and again once in the BVH.
+        for build in [false, true] {
+            let mut world = World::new();
+            world.add(sheet(-1.0, 0.0));
+            world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, -3.0), 1.0, ball.clone())));
+            if build {
+                world.build();
+            }
+
+            let mut rec = HitRecord::default();
+            assert!(world.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+            assert!((rec.t - 2.0).abs() < 1e-9 && rec.material == ball);
+            assert!(!world.hit(&ray, 0.001, 1.5, &mut rec));
+        }
+
+        // Nothing there at all.
+        let mut world = World::new();
+        world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, -3.0), 1.0, ball.with_alpha(Texture::Constant(Color::default())))));
+        world.build();
+        assert!(!world.hit(&ray, 0.001, f64::INFINITY, &mut HitRecord::default()));
+    }
+
+    #[test]
+    fn test_transmittance_through_partly_there() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut world = World::new();
+        world.add(sheet(-1.0, 0.5));
+        world.add(sheet(-2.0, 0.0));
+        world.build();
+
+        assert!((world.transmittance(&ray, 0.001, f64::INFINITY) - 0.5).abs() < 1e-12);
+        assert_eq!(world.transmittance(&ray, 0.001, 0.5), 1.0);
+    }
+}